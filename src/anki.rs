@@ -0,0 +1,229 @@
+use crate::FourButton;
+
+/// The settings of `AnkiSm2`, mirroring Anki's deck options.
+/// The `Default` matches Anki's defaults.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnkiConfig {
+    /// The ease of new cards, in permille.
+    pub starting_ease: u16,
+    /// The interval in days given to a new card answered `Good`.
+    pub graduating_interval: u32,
+    /// The interval in days given to a new card answered `Easy`.
+    pub easy_interval: u32,
+    /// The extra multiplier applied to the interval of a card answered `Easy`.
+    pub easy_bonus: f64,
+    /// The multiplier applied to the interval of a card answered `Hard`.
+    pub hard_interval: f64,
+    /// The multiplier applied to all review intervals.
+    pub interval_modifier: f64,
+    /// The maximum interval in days.
+    pub maximum_interval: u32,
+    /// The multiplier applied to the interval of a card answered `Again`.
+    pub new_interval: f64,
+    /// The minimum interval in days of a card answered `Again`.
+    pub minimum_interval: u32,
+}
+
+impl Default for AnkiConfig {
+    fn default() -> Self {
+        Self {
+            starting_ease: 2500,
+            graduating_interval: 1,
+            easy_interval: 4,
+            easy_bonus: 1.3,
+            hard_interval: 1.2,
+            interval_modifier: 1.0,
+            maximum_interval: 36500,
+            new_interval: 0.0,
+            minimum_interval: 1,
+        }
+    }
+}
+
+/// The lowest ease a card can have, in permille.
+pub const ANKI_MINIMUM_EASE: u16 = 1300;
+
+/// The scheduling state of a card under `AnkiSm2`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnkiCard {
+    /// The ease factor, in permille.
+    ease: u16,
+    /// The current interval in days, or 0 for a card that has not graduated yet.
+    interval: u32,
+}
+
+impl AnkiCard {
+    /// Return an `AnkiCard` with the given ease in permille and interval in days,
+    /// e.g. when importing a card from Anki.
+    pub fn new(ease: u16, interval: u32) -> Self {
+        Self { ease, interval }
+    }
+
+    /// Get the ease factor in permille, e.g. 2500 for 250%.
+    pub fn ease(&self) -> u16 {
+        self.ease
+    }
+
+    /// Get the current interval in days.
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    /// Returns whether the card has not graduated to the review queue yet.
+    pub fn is_new(&self) -> bool {
+        self.interval == 0
+    }
+}
+
+/// The SM-2 derived scheduler used by Anki's v2 and v3 schedulers for review cards.
+///
+/// Compared to SM-2, the ease only changes by fixed amounts (-200‰ on `Again`, -150‰ on `Hard`
+/// and +150‰ on `Easy`), reviews answered late get credit for the extra time, and intervals are
+/// scaled by the hard multiplier, easy bonus and interval modifier. Learning steps are not
+/// modelled: a new card graduates as soon as it is answered `Good` or `Easy`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnkiSm2 {
+    config: AnkiConfig,
+}
+
+impl AnkiSm2 {
+    /// Return an `AnkiSm2` with the given settings.
+    pub fn new(config: AnkiConfig) -> Self {
+        Self { config }
+    }
+
+    /// Get the settings of this scheduler.
+    pub fn config(&self) -> &AnkiConfig {
+        &self.config
+    }
+
+    /// Return the state of a card that has never been reviewed.
+    pub fn new_card(&self) -> AnkiCard {
+        AnkiCard::new(self.config.starting_ease, 0)
+    }
+
+    /// Returns the new state of `card` after answering it with `button`, `elapsed_days` days
+    /// after its previous review.
+    pub fn review(&self, card: &AnkiCard, button: FourButton, elapsed_days: u32) -> AnkiCard {
+        if card.is_new() {
+            let interval = match button {
+                FourButton::Again | FourButton::Hard => 0,
+                FourButton::Good => self.config.graduating_interval,
+                FourButton::Easy => self.config.easy_interval,
+            };
+            return AnkiCard::new(card.ease, interval);
+        }
+
+        let ease = match button {
+            FourButton::Again => card.ease.saturating_sub(200),
+            FourButton::Hard => card.ease.saturating_sub(150),
+            FourButton::Good => card.ease,
+            FourButton::Easy => card.ease.saturating_add(150),
+        }
+        .max(ANKI_MINIMUM_EASE);
+
+        let interval = match button {
+            FourButton::Again => self.lapse_interval(card),
+            _ => self.review_interval(card, button, elapsed_days),
+        };
+
+        AnkiCard::new(ease, interval)
+    }
+
+    fn lapse_interval(&self, card: &AnkiCard) -> u32 {
+        let interval = (card.interval as f64 * self.config.new_interval) as u32;
+        interval.max(self.config.minimum_interval).max(1)
+    }
+
+    fn review_interval(&self, card: &AnkiCard, button: FourButton, elapsed_days: u32) -> u32 {
+        let interval = card.interval as f64;
+        let days_late = elapsed_days.saturating_sub(card.interval) as f64;
+        let ease = card.ease as f64 / 1000.0;
+
+        let hard_minimum = if self.config.hard_interval > 1.0 {
+            card.interval
+        } else {
+            0
+        };
+        let hard = self.constrain(interval * self.config.hard_interval, hard_minimum);
+        if button == FourButton::Hard {
+            return hard;
+        }
+
+        let good = self.constrain((interval + (days_late / 2.0).floor()) * ease, hard);
+        if button == FourButton::Good {
+            return good;
+        }
+
+        self.constrain((interval + days_late) * ease * self.config.easy_bonus, good)
+    }
+
+    /// Apply the interval modifier, then make sure the interval is longer than `previous` and
+    /// no longer than the maximum interval.
+    fn constrain(&self, interval: f64, previous: u32) -> u32 {
+        let interval = (interval * self.config.interval_modifier) as u32;
+        interval
+            .max(previous.saturating_add(1))
+            .min(self.config.maximum_interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intervals(card: AnkiCard, elapsed_days: u32) -> [u32; 4] {
+        let anki = AnkiSm2::default();
+        let mut intervals = [0; 4];
+        let buttons = [
+            FourButton::Again,
+            FourButton::Hard,
+            FourButton::Good,
+            FourButton::Easy,
+        ];
+        for (interval, button) in intervals.iter_mut().zip(buttons.iter()) {
+            *interval = anki.review(&card, *button, elapsed_days).interval();
+        }
+        intervals
+    }
+
+    #[test]
+    fn matches_anki_on_time() {
+        assert_eq!(intervals(AnkiCard::new(2500, 10), 10), [1, 12, 25, 32]);
+        assert_eq!(intervals(AnkiCard::new(1300, 1), 1), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn matches_anki_when_late() {
+        assert_eq!(intervals(AnkiCard::new(2500, 10), 14), [1, 12, 30, 45]);
+        assert_eq!(
+            intervals(AnkiCard::new(2500, 30000), 30000),
+            [1, 36000, 36500, 36500]
+        );
+    }
+
+    #[test]
+    fn ease_changes() {
+        let anki = AnkiSm2::default();
+        let card = AnkiCard::new(2500, 10);
+        assert_eq!(anki.review(&card, FourButton::Again, 10).ease(), 2300);
+        assert_eq!(anki.review(&card, FourButton::Hard, 10).ease(), 2350);
+        assert_eq!(anki.review(&card, FourButton::Good, 10).ease(), 2500);
+        assert_eq!(anki.review(&card, FourButton::Easy, 10).ease(), 2650);
+        let card = AnkiCard::new(1400, 10);
+        assert_eq!(anki.review(&card, FourButton::Again, 10).ease(), 1300);
+    }
+
+    #[test]
+    fn new_cards_graduate() {
+        let anki = AnkiSm2::default();
+        let card = anki.new_card();
+        assert!(anki.review(&card, FourButton::Hard, 0).is_new());
+        assert_eq!(anki.review(&card, FourButton::Good, 0).interval(), 1);
+        assert_eq!(anki.review(&card, FourButton::Easy, 0).interval(), 4);
+        assert_eq!(anki.review(&card, FourButton::Easy, 0).ease(), 2500);
+    }
+}
@@ -0,0 +1,572 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use crate::{Date, Error, Item, LeechAction, Quality, RandomSource, Scheduled, Sm2Config};
+
+/// A record of a single review of a `Card`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReviewLog<D = u64> {
+    /// The date of the review.
+    pub reviewed_at: D,
+    /// The quality the review was graded with.
+    pub quality: Quality,
+    /// The E-factor before the review.
+    pub previous_efactor: f64,
+    /// The E-factor after the review.
+    pub new_efactor: f64,
+    /// The number of days the card was scheduled for before the review.
+    pub previous_interval: usize,
+    /// The number of days the card is scheduled for after the review.
+    pub new_interval: usize,
+    /// The number of days since the previous review, or `None` for the first review.
+    pub elapsed_days: Option<i64>,
+    /// How long the learner took to answer, if it was measured.
+    pub response_time: Option<Duration>,
+    /// Whether this review lapsed the card enough times to make it a leech.
+    /// See [`Sm2Config::leech_threshold`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub became_leech: bool,
+}
+
+/// Where a `Card` is in its lifecycle. See [`Card::state`].
+///
+/// A `Card` is scheduled in whole days by SM-2, so the drill states only mean that its `Item`
+/// still needs to be drilled on the day of its review (see [`Item::needs_drill`]). They are
+/// unrelated to the learning steps of [`LearningPhase`](crate::LearningPhase).
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CardState<D = u64> {
+    /// The card has never been reviewed.
+    New,
+    /// The card has not been recalled since it was new, and still needs to be drilled today.
+    Drill { item: Item },
+    /// The card lapsed after being learned, and still needs to be drilled today.
+    /// See [`Item::lapses`].
+    LapseDrill { item: Item },
+    /// The card is scheduled in days by SM-2, using the given `Item`.
+    Review { item: Item },
+    /// The card is excluded from reviews until it is unsuspended.
+    Suspended,
+    /// The card is excluded from reviews until the given date.
+    Buried { until: D },
+}
+
+/// The number of reviews that can be undone by default.
+pub const DEFAULT_UNDO_DEPTH: usize = 16;
+
+/// A `Scheduled` item together with a log of all of its reviews.
+///
+/// The most recent reviews can be undone and redone. The undo and redo stacks are not
+/// serialized.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "D: serde::Serialize",
+        deserialize = "D: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Card<D = u64> {
+    scheduled: Scheduled<D>,
+    history: Vec<ReviewLog<D>>,
+    /// The states and whether the card was suspended before each of the most recent reviews,
+    /// oldest first.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    undo: VecDeque<(Scheduled<D>, bool)>,
+    /// The undone states, whether the card was suspended and their logs, most recently undone
+    /// last.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    redo: Vec<(Scheduled<D>, bool, ReviewLog<D>)>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_undo_depth"))]
+    undo_depth: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    suspended: bool,
+    /// The date until which the card is buried, if it has been buried.
+    #[cfg_attr(feature = "serde", serde(default))]
+    buried_until: Option<D>,
+}
+
+#[cfg(feature = "serde")]
+fn default_undo_depth() -> usize {
+    DEFAULT_UNDO_DEPTH
+}
+
+impl<D> Default for Card<D> {
+    /// Return a `Card` for a default `Item` that has never been reviewed.
+    fn default() -> Self {
+        Self::new(Item::default())
+    }
+}
+
+impl<D> From<Item> for Card<D> {
+    fn from(item: Item) -> Self {
+        Self::new(item)
+    }
+}
+
+impl<D> From<Scheduled<D>> for Card<D> {
+    fn from(scheduled: Scheduled<D>) -> Self {
+        Self {
+            scheduled,
+            history: Vec::new(),
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            undo_depth: DEFAULT_UNDO_DEPTH,
+            suspended: false,
+            buried_until: None,
+        }
+    }
+}
+
+impl<D> Card<D> {
+    /// Return a `Card` for an `Item` that has never been reviewed, with an empty history.
+    pub fn new(item: Item) -> Self {
+        Scheduled::new(item).into()
+    }
+
+    /// Get the scheduling state of this `Card`.
+    pub fn scheduled(&self) -> &Scheduled<D> {
+        &self.scheduled
+    }
+
+    /// Get the underlying `Item`.
+    pub fn item(&self) -> &Item {
+        self.scheduled.item()
+    }
+
+    /// Get the log of all reviews of this `Card`, oldest first.
+    pub fn history(&self) -> &[ReviewLog<D>] {
+        &self.history
+    }
+
+    /// Returns whether this `Card` is suspended.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Suspend this `Card`, excluding it from reviews until it is unsuspended.
+    pub fn suspend(&mut self) {
+        self.suspended = true;
+    }
+
+    /// Unsuspend this `Card`. Any burial is left in place.
+    pub fn unsuspend(&mut self) {
+        self.suspended = false;
+    }
+
+    /// Unbury this `Card` before its burial ends.
+    pub fn unbury(&mut self) {
+        self.buried_until = None;
+    }
+
+    /// Return this `Card` with the given maximum number of reviews that can be undone.
+    pub fn with_undo_depth(mut self, undo_depth: usize) -> Self {
+        self.set_undo_depth(undo_depth);
+        self
+    }
+
+    /// Set the maximum number of reviews that can be undone, dropping the oldest ones if needed.
+    pub(crate) fn set_undo_depth(&mut self, undo_depth: usize) {
+        self.undo_depth = undo_depth;
+        self.undo
+            .drain(..self.undo.len().saturating_sub(undo_depth));
+    }
+}
+
+impl<D: Copy> Card<D> {
+    /// Undo the most recent review, restoring the exact state before it including the due date.
+    /// Any drills since that review are discarded as well.
+    /// Returns the log of the undone review, or `None` if there is nothing to undo.
+    pub fn undo_last_review(&mut self) -> Option<&ReviewLog<D>> {
+        let (scheduled, suspended) = self.undo.pop_back()?;
+        let log = self.history.pop()?;
+        self.redo.push((self.scheduled, self.suspended, log));
+        self.scheduled = scheduled;
+        self.suspended = suspended;
+        self.redo.last().map(|(_, _, log)| log)
+    }
+
+    /// Redo the most recently undone review.
+    /// Returns the log of the redone review, or `None` if there is nothing to redo.
+    pub fn redo(&mut self) -> Option<&ReviewLog<D>> {
+        let (scheduled, suspended, log) = self.redo.pop()?;
+        self.push_undo(self.scheduled);
+        self.scheduled = scheduled;
+        self.suspended = suspended;
+        self.history.push(log);
+        self.history.last()
+    }
+
+    fn push_undo(&mut self, scheduled: Scheduled<D>) {
+        if self.undo_depth == 0 {
+            return;
+        }
+        if self.undo.len() == self.undo_depth {
+            self.undo.pop_front();
+        }
+        self.undo.push_back((scheduled, self.suspended));
+    }
+}
+
+impl<D: Date> Card<D> {
+    /// Get the lifecycle state of this `Card` on `now`.
+    /// Suspension takes precedence over burial, which takes precedence over the review state.
+    pub fn state(&self, now: D) -> CardState<D> {
+        if self.suspended {
+            return CardState::Suspended;
+        }
+        if let Some(until) = self.buried_until.filter(|&until| now < until) {
+            return CardState::Buried { until };
+        }
+
+        let item = *self.item();
+        if self.scheduled.last_review().is_none() {
+            CardState::New
+        } else if item.needs_drill() && item.repetitions() <= 1 {
+            if item.lapses() == 0 {
+                CardState::Drill { item }
+            } else {
+                CardState::LapseDrill { item }
+            }
+        } else {
+            CardState::Review { item }
+        }
+    }
+
+    /// Returns whether this `Card` can be reviewed on `now`, i.e. it is neither suspended nor
+    /// buried.
+    pub fn is_available(&self, now: D) -> bool {
+        !self.suspended && self.buried_until.is_none_or(|until| until <= now)
+    }
+
+    /// Bury this `Card` until the day after `now`.
+    pub fn bury(&mut self, now: D) {
+        self.buried_until = Some(now.add_days(1));
+    }
+
+    /// Reset this `Card` to a new item, as if it had never been reviewed.
+    /// The review history is kept, but the reviews can no longer be undone.
+    pub fn forget(&mut self) {
+        self.forget_with_config(&Sm2Config::default());
+    }
+
+    /// Reset this `Card` to a new item of the given `Sm2Config`, as if it had never been
+    /// reviewed. The review history is kept, but the reviews can no longer be undone.
+    pub fn forget_with_config(&mut self, config: &Sm2Config) {
+        self.scheduled = Scheduled::new(config.new_item());
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Review this `Card` with the given quality on `now`, appending the review to its history.
+    /// See [`Item::review`] for the meaning of the quality.
+    /// Returns the log of the review, or `None` if the review was early and ignored by the
+    /// `EarlyReviewPolicy`, in which case it is recorded like [`Card::drill`].
+    pub fn review_at(
+        &mut self,
+        quality: u8,
+        now: D,
+        response_time: Option<Duration>,
+    ) -> Result<Option<&ReviewLog<D>>, Error> {
+        self.review_at_with_config(quality, now, response_time, &Sm2Config::default())
+    }
+
+    /// Review this `Card` with the given quality on `now` using the given `Sm2Config`,
+    /// appending the review to its history.
+    /// If the card is suspended or buried on `now`, this will return an `Err`.
+    pub fn review_at_with_config(
+        &mut self,
+        quality: u8,
+        now: D,
+        response_time: Option<Duration>,
+        config: &Sm2Config,
+    ) -> Result<Option<&ReviewLog<D>>, Error> {
+        self.apply(quality, now, response_time, config, |scheduled| {
+            scheduled.review_at_with_config(quality, now, config)
+        })
+    }
+
+    /// Review this `Card` with the given quality on `now` using the given `Sm2Config`, with the
+    /// new interval fuzzed using `rng`. See [`Scheduled::review_at_fuzzed`].
+    pub fn review_at_fuzzed<R: RandomSource + ?Sized>(
+        &mut self,
+        quality: u8,
+        now: D,
+        response_time: Option<Duration>,
+        config: &Sm2Config,
+        rng: &mut R,
+    ) -> Result<Option<&ReviewLog<D>>, Error> {
+        self.apply(quality, now, response_time, config, |scheduled| {
+            scheduled.review_at_fuzzed(quality, now, config, rng)
+        })
+    }
+
+    /// Review this `Card` with the given quality on `now` using the given `Sm2Config`, moving
+    /// the due date to the least busy nearby day. See [`Scheduled::review_at_balanced`].
+    pub fn review_at_balanced<F: FnMut(D) -> usize>(
+        &mut self,
+        quality: u8,
+        now: D,
+        response_time: Option<Duration>,
+        config: &Sm2Config,
+        due_count: F,
+    ) -> Result<Option<&ReviewLog<D>>, Error> {
+        self.apply(quality, now, response_time, config, |scheduled| {
+            scheduled.review_at_balanced(quality, now, config, due_count)
+        })
+    }
+
+    /// Review this `Card` with `review`, recording the review unless it was ignored.
+    fn apply<F>(
+        &mut self,
+        quality: u8,
+        now: D,
+        response_time: Option<Duration>,
+        config: &Sm2Config,
+        review: F,
+    ) -> Result<Option<&ReviewLog<D>>, Error>
+    where
+        F: FnOnce(&Scheduled<D>) -> Result<Scheduled<D>, Error>,
+    {
+        self.check_available(now)?;
+        let next = review(&self.scheduled)?;
+        let quality = Quality::try_from(quality)?;
+        if self.scheduled.ignores_review(now, config) {
+            // Ignored reviews only drill the item, like `Card::drill`.
+            self.scheduled = next;
+            return Ok(None);
+        }
+        Ok(Some(self.record(next, quality, now, response_time, config)))
+    }
+
+    /// Returns an `Err` if the card is suspended or buried on `now`.
+    fn check_available(&self, now: D) -> Result<(), Error> {
+        if self.is_available(now) {
+            Ok(())
+        } else {
+            Err(Error::CardUnavailableError)
+        }
+    }
+
+    /// Replace the scheduling state with the result of a review, logging the review.
+    fn record(
+        &mut self,
+        next: Scheduled<D>,
+        quality: Quality,
+        now: D,
+        response_time: Option<Duration>,
+        config: &Sm2Config,
+    ) -> &ReviewLog<D> {
+        let previous = std::mem::replace(&mut self.scheduled, next);
+        self.push_undo(previous);
+        self.redo.clear();
+
+        self.history.push(ReviewLog {
+            reviewed_at: now,
+            quality,
+            previous_efactor: previous.item().efactor(),
+            new_efactor: self.item().efactor(),
+            previous_interval: previous.scheduled_interval().unwrap_or(0),
+            new_interval: self.scheduled.scheduled_interval().unwrap_or(0),
+            elapsed_days: previous.last_review().map(|last| now.days_since(last)),
+            response_time,
+            became_leech: !config.is_leech(previous.item()) && config.is_leech(self.item()),
+        });
+        if self.history[self.history.len() - 1].became_leech
+            && config.leech_action == LeechAction::Suspend
+        {
+            self.suspended = true;
+        }
+
+        &self.history[self.history.len() - 1]
+    }
+
+    /// Drill this `Card` on the day of its review. Drills are not added to the history.
+    /// See [`Item::drill`].
+    pub fn drill(&mut self, quality: u8) -> Result<(), Error> {
+        self.scheduled = self.scheduled.drill(quality)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reviews_are_logged() {
+        let mut card = Card::default();
+        card.review_at(4, 10, None).unwrap();
+        let log = *card
+            .review_at(3, 12, Some(Duration::from_secs(5)))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(card.history().len(), 2);
+        assert_eq!(card.history()[0].elapsed_days, None);
+        assert_eq!(log.reviewed_at, 12);
+        assert_eq!(log.quality, Quality::CorrectHard);
+        assert_eq!(log.previous_efactor, 2.5);
+        assert_eq!(log.new_efactor, card.item().efactor());
+        assert_eq!(log.previous_interval, 1);
+        assert_eq!(log.new_interval, 6);
+        assert_eq!(log.elapsed_days, Some(2));
+        assert_eq!(log.response_time, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn undo_then_same_review_is_identical() {
+        let mut card = Card::default();
+        card.review_at(4, 10, None).unwrap();
+        card.review_at(5, 11, None).unwrap();
+        let reviewed = *card.scheduled();
+
+        assert_eq!(card.undo_last_review().unwrap().reviewed_at, 11);
+        assert_eq!(card.scheduled().due_date(), Some(11));
+        assert_eq!(card.history().len(), 1);
+
+        assert_eq!(card.redo().unwrap().reviewed_at, 11);
+        assert_eq!(*card.scheduled(), reviewed);
+        assert!(card.redo().is_none());
+
+        card.undo_last_review().unwrap();
+        card.review_at(5, 11, None).unwrap();
+        assert_eq!(*card.scheduled(), reviewed);
+        assert!(card.redo().is_none());
+    }
+
+    #[test]
+    fn undo_depth_is_bounded() {
+        let mut card = Card::default().with_undo_depth(2);
+        for day in 0..5 {
+            card.review_at(4, day, None).unwrap();
+        }
+        assert!(card.undo_last_review().is_some());
+        assert!(card.undo_last_review().is_some());
+        assert!(card.undo_last_review().is_none());
+        assert_eq!(card.scheduled().last_review(), Some(2));
+        assert_eq!(card.history().len(), 3);
+    }
+
+    #[test]
+    fn crossing_leech_threshold_is_logged() {
+        let config = Sm2Config {
+            leech_threshold: Some(2),
+            ..Sm2Config::default()
+        };
+        let mut card = Card::default();
+        let mut leech_reviews = Vec::new();
+        for (day, quality) in [4, 1, 4, 1, 4, 0].iter().enumerate() {
+            let log = card
+                .review_at_with_config(*quality, day as u64, None, &config)
+                .unwrap()
+                .unwrap();
+            leech_reviews.push(log.became_leech);
+        }
+        assert_eq!(leech_reviews, [false, false, false, true, false, false]);
+        assert_eq!(card.item().lapses(), 3);
+        assert!(config.is_leech(card.item()));
+    }
+
+    #[test]
+    fn lifecycle_states() {
+        let mut card = Card::default();
+        assert_eq!(card.state(10), CardState::New);
+        card.review_at(3, 10, None).unwrap();
+        assert_eq!(card.state(10), CardState::Drill { item: *card.item() });
+        card.drill(4).unwrap();
+        assert_eq!(card.state(10), CardState::Review { item: *card.item() });
+        card.review_at(4, 11, None).unwrap();
+        card.review_at(1, 17, None).unwrap();
+        let lapsed = CardState::LapseDrill { item: *card.item() };
+        assert_eq!(card.state(17), lapsed);
+
+        card.bury(17);
+        assert_eq!(card.state(17), CardState::Buried { until: 18 });
+        assert!(matches!(
+            card.review_at(4, 17, None),
+            Err(Error::CardUnavailableError)
+        ));
+        assert_eq!(card.history().len(), 3);
+        assert!(!card.is_available(17));
+        assert!(card.is_available(18));
+        card.suspend();
+        assert_eq!(card.state(18), CardState::Suspended);
+        card.unsuspend();
+        assert_eq!(card.state(18), lapsed);
+
+        card.forget();
+        assert_eq!(card.state(18), CardState::New);
+        assert_eq!(card.history().len(), 3);
+        assert!(card.undo_last_review().is_none());
+    }
+
+    #[test]
+    fn repeated_failures_of_new_card_are_not_lapses() {
+        let mut card = Card::default();
+        card.review_at(1, 10, None).unwrap();
+        card.review_at(0, 11, None).unwrap();
+        assert_eq!(card.item().lapses(), 0);
+        assert_eq!(card.state(11), CardState::Drill { item: *card.item() });
+    }
+
+    #[test]
+    fn leeches_can_be_suspended() {
+        let config = Sm2Config {
+            leech_threshold: Some(1),
+            leech_action: LeechAction::Suspend,
+            ..Sm2Config::default()
+        };
+        let mut card = Card::new(Item::new(3, 2.5));
+        card.review_at_with_config(1, 10, None, &config).unwrap();
+        assert!(card.is_suspended());
+
+        card.undo_last_review().unwrap();
+        assert!(!card.is_suspended());
+        assert_eq!(card.item().lapses(), 0);
+        card.redo().unwrap();
+        assert!(card.is_suspended());
+        assert_eq!(card.item().lapses(), 1);
+    }
+
+    #[test]
+    fn ignored_early_review_is_a_drill() {
+        let config = Sm2Config {
+            early_review: crate::EarlyReviewPolicy::Ignore,
+            ..Sm2Config::default()
+        };
+        let mut card = Card::default();
+        card.review_at(4, 0, None).unwrap();
+        card.review_at(4, 1, None).unwrap();
+        let reviewed = *card.scheduled();
+        let mut rng = crate::SplitMix64::new(1);
+
+        assert!(card
+            .review_at_with_config(2, 3, None, &config)
+            .unwrap()
+            .is_none());
+        assert!(card
+            .review_at_fuzzed(5, 3, None, &config, &mut rng)
+            .unwrap()
+            .is_none());
+        assert!(card
+            .review_at_balanced(5, 3, None, &config, |_| 0)
+            .unwrap()
+            .is_none());
+        assert_eq!(card.history().len(), 2);
+        assert_eq!(card.scheduled().due_date(), reviewed.due_date());
+        assert_eq!(card.item().repetitions(), 2);
+        assert_eq!(card.undo_last_review().unwrap().reviewed_at, 1);
+    }
+
+    #[test]
+    fn invalid_review_is_not_logged() {
+        let mut card = Card::<u64>::default();
+        assert!(card.review_at(6, 10, None).is_err());
+        assert!(card.history().is_empty());
+        assert_eq!(card.scheduled().last_review(), None);
+    }
+}
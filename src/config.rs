@@ -0,0 +1,194 @@
+use crate::{EarlyReviewPolicy, Item, Quality, SchedulingMode};
+
+/// What happens to a `Card` when it becomes a leech. See [`Sm2Config::leech_threshold`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LeechAction {
+    /// Only flag the review in the log with `ReviewLog::became_leech`. This is the default.
+    #[default]
+    Flag,
+    /// Flag the review and suspend the card.
+    Suspend,
+}
+
+/// The parameters of the SM-2 algorithm.
+/// The `Default` is the algorithm as published, which is what `Item::review` and
+/// `Item::interval` use.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Sm2Config {
+    /// The E-factor of new items.
+    pub initial_efactor: f64,
+    /// The lowest E-factor. Both the E-factor used in the update and the updated E-factor are
+    /// kept at or above this.
+    pub min_efactor: f64,
+    /// The highest E-factor, if any. The updated E-factor is kept at or below this.
+    pub max_efactor: Option<f64>,
+    /// The constant term of the E-factor update, `a` in `EF' = EF + (a - (5-q) * (b + (5-q) * c))`.
+    pub efactor_constant: f64,
+    /// The linear term of the E-factor update, `b` in `EF' = EF + (a - (5-q) * (b + (5-q) * c))`.
+    pub efactor_linear: f64,
+    /// The quadratic term of the E-factor update, `c` in `EF' = EF + (a - (5-q) * (b + (5-q) * c))`.
+    pub efactor_quadratic: f64,
+    /// The interval in days after the first successful review.
+    pub first_interval: usize,
+    /// The interval in days after the second successful review.
+    pub second_interval: usize,
+    /// The lowest quality that counts as a successful review. Lower qualities restart the
+    /// repetitions.
+    pub pass_threshold: Quality,
+    /// The factor all intervals are multiplied by, including when calculating due dates.
+    pub interval_multiplier: f64,
+    /// The longest interval in days, if any, e.g. `Some(36500)` for 100 years.
+    /// It is applied after the interval multiplier, including when calculating due dates.
+    pub max_interval: Option<usize>,
+    /// How intervals are derived from the review history.
+    pub mode: SchedulingMode,
+    /// How reviews before the due date are graded.
+    pub early_review: EarlyReviewPolicy,
+    /// The number of lapses at which an item counts as a leech, if any.
+    /// Leeches are items that keep being forgotten and may need to be rewritten or suspended.
+    pub leech_threshold: Option<usize>,
+    /// What happens to a `Card` when it becomes a leech.
+    pub leech_action: LeechAction,
+}
+
+impl Default for Sm2Config {
+    fn default() -> Self {
+        Self {
+            initial_efactor: 2.5,
+            min_efactor: 1.3,
+            max_efactor: None,
+            efactor_constant: 0.1,
+            efactor_linear: 0.08,
+            efactor_quadratic: 0.02,
+            first_interval: 1,
+            second_interval: 6,
+            pass_threshold: Quality::CorrectHard,
+            interval_multiplier: 1.0,
+            max_interval: None,
+            mode: SchedulingMode::ClosedForm,
+            early_review: EarlyReviewPolicy::Full,
+            leech_threshold: Some(8),
+            leech_action: LeechAction::Flag,
+        }
+    }
+}
+
+impl Sm2Config {
+    /// Return a new `Item` with 0 repetitions and the initial E-factor.
+    pub fn new_item(&self) -> Item {
+        Item::new(0, self.initial_efactor)
+    }
+
+    /// Returns the E-factor after a review with the given quality, within the E-factor bounds.
+    pub fn next_efactor(&self, efactor: f64, quality: Quality) -> f64 {
+        let q = quality.value() as f64;
+
+        // EF':=EF+(0.1-(5-q)*(0.08+(5-q)*0.02))
+        let ef = self.bound_efactor(efactor)
+            + (self.efactor_constant
+                - (5.0 - q) * (self.efactor_linear + (5.0 - q) * self.efactor_quadratic));
+
+        self.bound_efactor(ef)
+    }
+
+    fn bound_efactor(&self, efactor: f64) -> f64 {
+        let efactor = if efactor < self.min_efactor {
+            self.min_efactor
+        } else {
+            efactor
+        };
+
+        match self.max_efactor {
+            Some(max) if efactor > max => max,
+            _ => efactor,
+        }
+    }
+
+    /// Returns whether the item has lapsed at least as many times as the leech threshold.
+    pub fn is_leech(&self, item: &Item) -> bool {
+        self.leech_threshold
+            .is_some_and(|threshold| item.lapses() >= threshold)
+    }
+
+    /// Returns whether the given quality counts as a successful review.
+    pub fn is_pass(&self, quality: Quality) -> bool {
+        quality >= self.pass_threshold
+    }
+
+    /// Apply the interval multiplier and maximum interval to an interval in days, rounding up.
+    /// Intervals too large to represent, including infinite ones, saturate at `usize::MAX`
+    /// before the maximum interval is applied.
+    pub(crate) fn scale_interval(&self, interval: f64) -> usize {
+        let interval = (interval * self.interval_multiplier).ceil() as usize;
+        interval.min(self.max_interval.unwrap_or(usize::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_matches_item() {
+        let config = Sm2Config::default();
+        let mut item = config.new_item();
+        let mut configured = config.new_item();
+        for quality in [5, 4, 3, 5, 2, 0, 4, 4].iter() {
+            item = item.review(*quality).unwrap();
+            configured = configured.review_with_config(*quality, &config).unwrap();
+            assert_eq!(item, configured);
+            assert_eq!(item.interval(), configured.interval_with_config(&config));
+        }
+    }
+
+    #[test]
+    fn custom_intervals() {
+        let config = Sm2Config {
+            first_interval: 2,
+            second_interval: 4,
+            interval_multiplier: 1.5,
+            max_interval: Some(10),
+            ..Sm2Config::default()
+        };
+        let item = config
+            .new_item()
+            .grade_with_config(Quality::CorrectHesitant, &config);
+        assert_eq!(item.interval_with_config(&config), 3);
+        let item = item.grade_with_config(Quality::CorrectHesitant, &config);
+        assert_eq!(item.interval_with_config(&config), 6);
+        let item = item.grade_with_config(Quality::CorrectHesitant, &config);
+        assert_eq!(item.interval_with_config(&config), 10);
+    }
+
+    #[test]
+    fn efactor_is_clamped_after_update() {
+        let config = Sm2Config::default();
+        assert_eq!(config.next_efactor(1.3, Quality::Blackout), 1.3);
+        assert_eq!(Item::new(0, 1.3).review(0).unwrap().efactor(), 1.3);
+
+        let config = Sm2Config {
+            max_efactor: Some(2.6),
+            ..Sm2Config::default()
+        };
+        assert_eq!(config.next_efactor(2.55, Quality::Perfect), 2.6);
+        assert_eq!(config.next_efactor(3.0, Quality::CorrectHesitant), 2.6);
+    }
+
+    #[test]
+    fn custom_efactor_and_threshold() {
+        let config = Sm2Config {
+            initial_efactor: 2.0,
+            min_efactor: 1.5,
+            pass_threshold: Quality::CorrectHesitant,
+            ..Sm2Config::default()
+        };
+        assert_eq!(config.new_item().efactor(), 2.0);
+        assert_eq!(config.next_efactor(1.0, Quality::Perfect), 1.6);
+
+        let item = Item::new(3, 2.5).grade_with_config(Quality::CorrectHard, &config);
+        assert_eq!(item.repetitions(), 1);
+    }
+}
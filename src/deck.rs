@@ -0,0 +1,611 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::mem;
+
+use crate::{fuzz_range, Card, Date, Error, Sm2Config, SplitMix64, Stats, DEFAULT_UNDO_DEPTH};
+
+/// A collection of `Card`s, keyed by user-provided IDs.
+///
+/// All cards in a deck are reviewed with the deck's `Sm2Config`, and their intervals are
+/// fuzzed if fuzzing has been enabled with [`Deck::with_fuzz`]. With [`Deck::with_load_balancing`],
+/// due dates are instead spread out by moving each review to the least busy day in the fuzz range.
+///
+/// Like `Card`, the deck can undo and redo its most recent reviews, whichever card they were
+/// for. The undo and redo stacks are not serialized.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "K: serde::Serialize, D: serde::Serialize",
+        deserialize = "K: serde::Deserialize<'de> + Eq + Hash, D: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Deck<K, D = u64> {
+    cards: HashMap<K, Card<D>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    config: Sm2Config,
+    /// The random number generator used to fuzz intervals, if fuzzing is enabled.
+    #[cfg_attr(feature = "serde", serde(default))]
+    fuzz: Option<SplitMix64>,
+    /// Whether due dates are moved to the least busy day in the fuzz range.
+    #[cfg_attr(feature = "serde", serde(default))]
+    load_balance: bool,
+    /// The keys of the most recently reviewed cards, oldest first, each with the state of the
+    /// random number generator before the review.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    undo: VecDeque<(K, Option<SplitMix64>)>,
+    /// The keys of the cards with undone reviews, most recently undone last, each with the state
+    /// of the random number generator before the undo.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    redo: Vec<(K, Option<SplitMix64>)>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_undo_depth"))]
+    undo_depth: usize,
+}
+
+#[cfg(feature = "serde")]
+fn default_undo_depth() -> usize {
+    DEFAULT_UNDO_DEPTH
+}
+
+impl<K, D> Default for Deck<K, D> {
+    fn default() -> Self {
+        Self {
+            cards: HashMap::new(),
+            config: Sm2Config::default(),
+            fuzz: None,
+            load_balance: false,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            undo_depth: DEFAULT_UNDO_DEPTH,
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash, D: Date> Deck<K, D> {
+    /// Return an empty `Deck`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return this `Deck` with the given SM-2 parameters.
+    pub fn with_config(mut self, config: Sm2Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Return this `Deck` with interval fuzzing enabled, using a random number generator seeded
+    /// with `seed` so that schedules are reproducible.
+    pub fn with_fuzz(mut self, seed: u64) -> Self {
+        self.fuzz = Some(SplitMix64::new(seed));
+        self
+    }
+
+    /// Return this `Deck` with load balancing enabled or disabled. When enabled, reviewed cards
+    /// are scheduled on the day with the fewest cards already due within the fuzz range of their
+    /// interval, and random fuzzing is not used.
+    pub fn with_load_balancing(mut self, load_balance: bool) -> Self {
+        self.load_balance = load_balance;
+        self
+    }
+
+    /// Get the SM-2 parameters of the deck.
+    pub fn config(&self) -> &Sm2Config {
+        &self.config
+    }
+
+    /// Get the number of cards in the deck.
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    /// Returns whether the deck has no cards.
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    /// Insert an item into the deck, returning the card previously stored under `key`, if any.
+    /// This accepts a `Card`, or an `Item` or `Scheduled` without a review history.
+    /// The card takes on the undo depth of the deck.
+    pub fn insert<C: Into<Card<D>>>(&mut self, key: K, card: C) -> Option<Card<D>> {
+        self.forget_undo(&key);
+        let mut card = card.into();
+        card.set_undo_depth(self.undo_depth);
+        self.cards.insert(key, card)
+    }
+
+    /// Remove a card from the deck, returning it if it was present.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Card<D>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.forget_undo(key);
+        self.cards.remove(key)
+    }
+
+    /// Get the card stored under `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&Card<D>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cards.get(key)
+    }
+
+    /// Iterate over all cards in the deck, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &Card<D>)> {
+        self.cards.iter()
+    }
+
+    /// Review the card stored under `key` with the given quality on `now`.
+    /// Returns the updated card, or an `Err` if there is no such card, it is suspended or buried,
+    /// or the quality is invalid.
+    pub fn review<Q>(&mut self, key: &Q, quality: u8, now: D) -> Result<&Card<D>, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let due_counts = if self.load_balance {
+            Some(self.due_counts_in_fuzz_range(key, quality, now))
+        } else {
+            None
+        };
+        let fuzz = self.fuzz.clone();
+        let card = self.cards.get_mut(key).ok_or(Error::ItemNotFoundError)?;
+        let recorded = match (&due_counts, &mut self.fuzz) {
+            (Some(due_counts), _) => {
+                let due_count = |day| due_counts.get(&day).copied().unwrap_or(0);
+                card.review_at_balanced(quality, now, None, &self.config, due_count)?
+            }
+            (None, Some(rng)) => card.review_at_fuzzed(quality, now, None, &self.config, rng)?,
+            (None, None) => card.review_at_with_config(quality, now, None, &self.config)?,
+        }
+        .is_some();
+
+        let (key, card) = self
+            .cards
+            .get_key_value(key)
+            .ok_or(Error::ItemNotFoundError)?;
+        if !recorded {
+            // The review was ignored, so like a drill it cannot be undone.
+            return Ok(card);
+        }
+        if self.undo_depth > 0 {
+            if self.undo.len() == self.undo_depth {
+                self.undo.pop_front();
+            }
+            self.undo.push_back((key.clone(), fuzz));
+        }
+        self.redo.clear();
+        Ok(card)
+    }
+
+    /// Return this `Deck` with the given maximum number of reviews that can be undone.
+    /// This also applies to the reviews of each card.
+    pub fn with_undo_depth(mut self, undo_depth: usize) -> Self {
+        self.undo_depth = undo_depth;
+        for card in self.cards.values_mut() {
+            card.set_undo_depth(undo_depth);
+        }
+        self.undo
+            .drain(..self.undo.len().saturating_sub(undo_depth));
+        self
+    }
+
+    /// Undo the most recent review in the deck. See [`Card::undo_last_review`].
+    /// The random number generator used for fuzzing is restored as well.
+    /// Returns the key and restored card, or `None` if there is nothing to undo.
+    pub fn undo_last_review(&mut self) -> Option<(&K, &Card<D>)> {
+        let (key, fuzz) = self.undo.pop_back()?;
+        self.cards.get_mut(&key)?.undo_last_review()?;
+        let fuzz = mem::replace(&mut self.fuzz, fuzz);
+        self.redo.push((key, fuzz));
+        self.cards.get_key_value(&self.redo.last()?.0)
+    }
+
+    /// Redo the most recently undone review in the deck. See [`Card::redo`].
+    /// The random number generator used for fuzzing is restored as well.
+    /// Returns the key and updated card, or `None` if there is nothing to redo.
+    pub fn redo(&mut self) -> Option<(&K, &Card<D>)> {
+        let (key, fuzz) = self.redo.pop()?;
+        self.cards.get_mut(&key)?.redo()?;
+        let fuzz = mem::replace(&mut self.fuzz, fuzz);
+        self.undo.push_back((key, fuzz));
+        self.cards.get_key_value(&self.undo.back()?.0)
+    }
+
+    /// Drop `key` from the undo and redo stacks, e.g. because its card was replaced.
+    fn forget_undo<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.undo.retain(|(k, _)| k.borrow() != key);
+        self.redo.retain(|(k, _)| k.borrow() != key);
+    }
+
+    /// Drill the card stored under `key` with the given quality, without affecting its schedule.
+    /// Returns the updated card, or an `Err` if there is no such card or the quality is invalid.
+    pub fn drill<Q>(&mut self, key: &Q, quality: u8) -> Result<&Card<D>, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let card = self.cards.get_mut(key).ok_or(Error::ItemNotFoundError)?;
+        card.drill(quality)?;
+        Ok(card)
+    }
+
+    /// Suspend the card stored under `key`. See [`Card::suspend`].
+    /// Returns the updated card, or an `Err` if there is no such card.
+    pub fn suspend<Q>(&mut self, key: &Q) -> Result<&Card<D>, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let card = self.cards.get_mut(key).ok_or(Error::ItemNotFoundError)?;
+        card.suspend();
+        Ok(card)
+    }
+
+    /// Unsuspend the card stored under `key`. See [`Card::unsuspend`].
+    /// Returns the updated card, or an `Err` if there is no such card.
+    pub fn unsuspend<Q>(&mut self, key: &Q) -> Result<&Card<D>, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let card = self.cards.get_mut(key).ok_or(Error::ItemNotFoundError)?;
+        card.unsuspend();
+        Ok(card)
+    }
+
+    /// Bury the card stored under `key` until the day after `now`. See [`Card::bury`].
+    /// Returns the updated card, or an `Err` if there is no such card.
+    pub fn bury<Q>(&mut self, key: &Q, now: D) -> Result<&Card<D>, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let card = self.cards.get_mut(key).ok_or(Error::ItemNotFoundError)?;
+        card.bury(now);
+        Ok(card)
+    }
+
+    /// Reset the card stored under `key` to a new item of the deck's `Sm2Config`.
+    /// See [`Card::forget_with_config`].
+    /// Returns the updated card, or an `Err` if there is no such card.
+    pub fn forget<Q>(&mut self, key: &Q) -> Result<&Card<D>, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.forget_undo(key);
+        let card = self.cards.get_mut(key).ok_or(Error::ItemNotFoundError)?;
+        card.forget_with_config(&self.config);
+        Ok(card)
+    }
+
+    /// Iterate over the cards that have been reviewed before and are due on `now`,
+    /// most urgent first. Suspended and buried cards are skipped.
+    pub fn due(&self, now: D) -> impl Iterator<Item = (&K, &Card<D>)> {
+        self.by_urgency(now, move |card| {
+            let scheduled = card.scheduled();
+            card.is_available(now) && scheduled.last_review().is_some() && scheduled.is_due(now)
+        })
+    }
+
+    /// Iterate over the cards that have been reviewed before and are past their due date on
+    /// `now`, most urgent first. Suspended and buried cards are skipped.
+    pub fn overdue(&self, now: D) -> impl Iterator<Item = (&K, &Card<D>)> {
+        self.by_urgency(now, move |card| {
+            card.is_available(now) && card.scheduled().days_overdue(now) > 0
+        })
+    }
+
+    /// Returns the number of cards due on each day, for every day that has cards due.
+    /// Cards that have never been reviewed have no due date and are not counted, and neither
+    /// are suspended cards.
+    pub fn due_counts(&self) -> BTreeMap<D, usize> {
+        let mut counts = BTreeMap::new();
+        for due in self
+            .cards
+            .values()
+            .filter(|card| !card.is_suspended())
+            .filter_map(|card| card.scheduled().due_date())
+        {
+            *counts.entry(due).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Returns the number of other cards due on each day that the card stored under `key` may be
+    /// load balanced to, when reviewed with the given quality on `now`. Only the days in the
+    /// [`fuzz_range`] of its new interval are counted, so that reviews stay cheap in large decks.
+    fn due_counts_in_fuzz_range<Q>(&self, key: &Q, quality: u8, now: D) -> BTreeMap<D, usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut counts = BTreeMap::new();
+        let target = self
+            .cards
+            .get(key)
+            .and_then(|card| {
+                card.scheduled()
+                    .review_at_with_config(quality, now, &self.config)
+                    .ok()
+            })
+            .and_then(|next| next.scheduled_interval());
+        let range = match target {
+            Some(target) => fuzz_range(target),
+            None => return counts,
+        };
+        let days = now.add_days(*range.start())..=now.add_days(*range.end());
+        for due in self
+            .cards
+            .iter()
+            .filter(|(k, card)| (*k).borrow() != key && !card.is_suspended())
+            .filter_map(|(_, card)| card.scheduled().due_date())
+            .filter(|due| days.contains(due))
+        {
+            *counts.entry(due).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Iterate over the cards that have lapsed at least as many times as the leech threshold of
+    /// the deck's `Sm2Config`, in arbitrary order.
+    pub fn leeches(&self) -> impl Iterator<Item = (&K, &Card<D>)> {
+        self.cards
+            .iter()
+            .filter(move |(_, card)| self.config.is_leech(card.item()))
+    }
+
+    /// Returns statistics about all cards in the deck as of `now`, using the deck's `Sm2Config`.
+    pub fn stats(&self, now: D) -> Stats {
+        Stats::from_cards_with_config(self.cards.values(), now, &self.config)
+    }
+
+    /// Iterate over the cards that have never been reviewed and can be studied on `now`,
+    /// in arbitrary order. Suspended and buried cards are skipped.
+    pub fn new_items(&self, now: D) -> impl Iterator<Item = (&K, &Card<D>)> {
+        self.cards.iter().filter(move |(_, card)| {
+            card.is_available(now) && card.scheduled().last_review().is_none()
+        })
+    }
+
+    /// Returns the matching cards sorted by the number of days overdue, breaking ties by
+    /// putting the cards with the lowest E-factor first.
+    fn by_urgency<F>(&self, now: D, filter: F) -> std::vec::IntoIter<(&K, &Card<D>)>
+    where
+        F: Fn(&Card<D>) -> bool,
+    {
+        let mut cards: Vec<_> = self.cards.iter().filter(|(_, card)| filter(card)).collect();
+        cards.sort_by(|(_, a), (_, b)| {
+            let overdue = |card: &Card<D>| card.scheduled().days_overdue(now);
+            overdue(b).cmp(&overdue(a)).then_with(|| {
+                a.item()
+                    .efactor()
+                    .partial_cmp(&b.item().efactor())
+                    .unwrap_or(Ordering::Equal)
+            })
+        });
+        cards.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fuzz_range, Item, Scheduled};
+
+    fn deck() -> Deck<&'static str> {
+        let mut deck = Deck::new();
+        deck.insert("new", Scheduled::new(Item::default()));
+        deck.insert("on-time", Scheduled::from_last_review(Item::new(1, 2.5), 9));
+        deck.insert("late", Scheduled::from_last_review(Item::new(2, 2.5), 1));
+        deck.insert("later", Scheduled::from_last_review(Item::new(2, 2.5), 0));
+        deck.insert("hard", Scheduled::from_last_review(Item::new(2, 1.3), 1));
+        deck.insert("future", Scheduled::from_last_review(Item::new(3, 2.5), 9));
+        deck
+    }
+
+    #[test]
+    fn due_items_are_sorted_by_urgency() {
+        let deck = deck();
+        let due: Vec<_> = deck.due(10).map(|(key, _)| *key).collect();
+        assert_eq!(due, ["later", "hard", "late", "on-time"]);
+        let overdue: Vec<_> = deck.overdue(10).map(|(key, _)| *key).collect();
+        assert_eq!(overdue, ["later", "hard", "late"]);
+        let new: Vec<_> = deck.new_items(10).map(|(key, _)| *key).collect();
+        assert_eq!(new, ["new"]);
+    }
+
+    #[test]
+    fn review_updates_item() {
+        let mut deck = deck();
+        let card = deck.review("new", 4, 10).unwrap();
+        assert_eq!(card.scheduled().due_date(), Some(11));
+        assert_eq!(card.history().len(), 1);
+        assert_eq!(deck.get("new").unwrap().item().repetitions(), 1);
+    }
+
+    #[test]
+    fn fuzzed_reviews_are_reproducible() {
+        let review = |seed| {
+            let mut deck = deck().with_fuzz(seed);
+            for key in ["late", "later", "hard", "on-time"].iter() {
+                deck.review(key, 5, 10).unwrap();
+            }
+            let mut due: Vec<_> = deck
+                .iter()
+                .map(|(key, card)| (*key, card.scheduled().due_date()))
+                .collect();
+            due.sort();
+            due
+        };
+        assert_eq!(review(3), review(3));
+        for (key, due) in review(3) {
+            let interval = deck()
+                .get(key)
+                .unwrap()
+                .item()
+                .review(5)
+                .unwrap()
+                .interval();
+            if key != "new" && key != "future" {
+                assert!(fuzz_range(interval).contains(&(due.unwrap() as usize - 10)));
+            }
+        }
+    }
+
+    #[test]
+    fn load_balancing_spreads_due_dates() {
+        let mut deck = deck().with_load_balancing(true);
+        assert_eq!(deck.due_counts().get(&24), Some(&1));
+        let counts = deck.due_counts_in_fuzz_range("late", 5, 10);
+        assert_eq!(counts.into_iter().collect::<Vec<_>>(), [(24, 1)]);
+
+        // Both cards get a 16 day interval, due on day 26 before balancing.
+        let late = deck.review("late", 5, 10).unwrap();
+        assert_eq!(late.scheduled().due_date(), Some(26));
+        let later = deck.review("later", 5, 10).unwrap();
+        assert_eq!(later.scheduled().due_date(), Some(25));
+        assert_eq!(deck.due_counts().range(24..=26).count(), 3);
+    }
+
+    #[test]
+    fn suspended_and_buried_cards_are_skipped() {
+        let mut deck = deck();
+        deck.suspend("later").unwrap();
+        deck.bury("hard", 10).unwrap();
+        deck.bury("new", 10).unwrap();
+        let due: Vec<_> = deck.due(10).map(|(key, _)| *key).collect();
+        assert_eq!(due, ["late", "on-time"]);
+        assert_eq!(deck.new_items(10).count(), 0);
+        assert_eq!(deck.due_counts().values().sum::<usize>(), 4);
+
+        let due: Vec<_> = deck.due(11).map(|(key, _)| *key).collect();
+        assert_eq!(due, ["hard", "late", "on-time"]);
+        assert_eq!(deck.new_items(11).count(), 1);
+
+        assert!(matches!(
+            deck.review("later", 4, 11),
+            Err(Error::CardUnavailableError)
+        ));
+        assert!(matches!(
+            deck.review("hard", 4, 10),
+            Err(Error::CardUnavailableError)
+        ));
+        assert!(deck.get("later").unwrap().history().is_empty());
+        assert!(deck.undo_last_review().is_none());
+
+        deck.unsuspend("later").unwrap();
+        assert_eq!(deck.overdue(11).count(), 3 + 1);
+        assert!(deck.suspend("missing").is_err());
+    }
+
+    #[test]
+    fn forget_resets_card() {
+        let mut deck = deck();
+        deck.review("late", 4, 10).unwrap();
+        let card = deck.forget("late").unwrap();
+        assert_eq!(card.state(10), crate::CardState::New);
+        assert_eq!(card.history().len(), 1);
+        assert!(deck.undo_last_review().is_none());
+    }
+
+    #[test]
+    fn leeches_use_deck_threshold() {
+        let mut deck = deck().with_config(Sm2Config {
+            leech_threshold: Some(1),
+            ..Sm2Config::default()
+        });
+        deck.insert("leech", Item::new(4, 1.3).with_lapses(1));
+        deck.review("late", 1, 10).unwrap();
+        let mut leeches: Vec<_> = deck.leeches().map(|(key, _)| *key).collect();
+        leeches.sort();
+        assert_eq!(leeches, ["late", "leech"]);
+
+        let deck = deck.with_config(Sm2Config {
+            leech_threshold: None,
+            ..Sm2Config::default()
+        });
+        assert_eq!(deck.leeches().count(), 0);
+    }
+
+    #[test]
+    fn undo_and_redo_across_cards() {
+        let mut deck = deck();
+        let before = *deck.get("late").unwrap().scheduled();
+        deck.review("late", 5, 10).unwrap();
+        let after = *deck.get("late").unwrap().scheduled();
+        deck.review("new", 3, 10).unwrap();
+
+        let (key, card) = deck.undo_last_review().unwrap();
+        assert_eq!(*key, "new");
+        assert!(card.history().is_empty());
+        let (key, card) = deck.undo_last_review().unwrap();
+        assert_eq!(*key, "late");
+        assert_eq!(*card.scheduled(), before);
+        assert!(deck.undo_last_review().is_none());
+
+        let (key, card) = deck.redo().unwrap();
+        assert_eq!(*key, "late");
+        assert_eq!(*card.scheduled(), after);
+
+        deck.remove("late");
+        assert!(deck.undo_last_review().is_none());
+
+        let mut deck = self::deck().with_fuzz(7);
+        deck.review("late", 5, 10).unwrap();
+        deck.review("later", 5, 10).unwrap();
+        let fuzzed = (
+            *deck.get("late").unwrap().scheduled(),
+            *deck.get("later").unwrap().scheduled(),
+        );
+        deck.undo_last_review().unwrap();
+        deck.undo_last_review().unwrap();
+        deck.review("late", 5, 10).unwrap();
+        deck.review("later", 5, 10).unwrap();
+        assert_eq!(*deck.get("late").unwrap().scheduled(), fuzzed.0);
+        assert_eq!(*deck.get("later").unwrap().scheduled(), fuzzed.1);
+
+        let mut replayed = self::deck().with_fuzz(7);
+        replayed.review("late", 5, 10).unwrap();
+        assert_eq!(deck.undo_last_review().unwrap().0, &"later");
+        assert_eq!(deck.fuzz, replayed.fuzz);
+        replayed.review("later", 5, 10).unwrap();
+        deck.redo().unwrap();
+        assert_eq!(deck.fuzz, replayed.fuzz);
+    }
+
+    #[test]
+    fn deck_undo_depth_applies_to_cards() {
+        let mut deck = deck().with_undo_depth(40);
+        deck.insert("added", Item::default());
+        for key in ["new", "added"] {
+            for day in 10..30 {
+                deck.review(key, 2, day).unwrap();
+            }
+        }
+        for _ in 0..40 {
+            deck.undo_last_review().unwrap();
+        }
+        assert!(deck.undo_last_review().is_none());
+        assert!(deck.get("new").unwrap().history().is_empty());
+        assert!(deck.get("added").unwrap().history().is_empty());
+    }
+
+    #[test]
+    fn review_of_missing_item_returns_error() {
+        let mut deck = deck();
+        assert!(deck.review("missing", 4, 10).is_err());
+        assert!(deck.remove("new").is_some());
+        assert!(deck.review("new", 4, 10).is_err());
+    }
+}
@@ -0,0 +1,267 @@
+use std::time::Duration;
+
+use crate::scheduler::{days, SECONDS_PER_DAY};
+use crate::{FourButton, Quality, Scheduler};
+
+/// The default weights of FSRS-4.5.
+pub const FSRS_DEFAULT_WEIGHTS: [f64; 17] = [
+    0.4872, 1.4003, 3.7145, 13.8206, 5.1618, 1.2298, 0.8975, 0.031, 1.6474, 0.1367, 1.0461, 2.1072,
+    0.0793, 0.3246, 1.587, 0.2272, 2.8755,
+];
+
+const DECAY: f64 = -0.5;
+/// Chosen so that the retrievability is 90% after `stability` days.
+const FACTOR: f64 = 19.0 / 81.0;
+
+/// The settings of `Fsrs`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FsrsConfig {
+    /// The model weights, e.g. as fitted to a learner's review logs.
+    pub weights: [f64; 17],
+    /// The probability of recall at which items are scheduled, from 0 to 1.
+    pub desired_retention: f64,
+    /// The maximum interval in days.
+    pub maximum_interval: u32,
+}
+
+impl Default for FsrsConfig {
+    /// Return a `FsrsConfig` with the default weights, 90% retention and a 100 year maximum.
+    fn default() -> Self {
+        Self {
+            weights: FSRS_DEFAULT_WEIGHTS,
+            desired_retention: 0.9,
+            maximum_interval: 36500,
+        }
+    }
+}
+
+/// The memory state of an item under `Fsrs`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FsrsState {
+    /// The number of days after which the probability of recall drops to 90%.
+    stability: f64,
+    /// How hard the item is, from 1 to 10.
+    difficulty: f64,
+}
+
+impl FsrsState {
+    /// Return a `FsrsState` with the given stability in days and difficulty from 1 to 10.
+    pub fn new(stability: f64, difficulty: f64) -> Self {
+        Self {
+            stability,
+            difficulty,
+        }
+    }
+
+    /// Get the stability in days.
+    pub fn stability(&self) -> f64 {
+        self.stability
+    }
+
+    /// Get the difficulty, from 1 to 10.
+    pub fn difficulty(&self) -> f64 {
+        self.difficulty
+    }
+
+    /// Returns the probability of recalling the item `elapsed_days` days after its last review.
+    pub fn retrievability(&self, elapsed_days: f64) -> f64 {
+        (1.0 + FACTOR * elapsed_days / self.stability).powf(DECAY)
+    }
+}
+
+/// The Free Spaced Repetition Scheduler, version 4.5.
+///
+/// Unlike SM-2, FSRS models the probability of recall of each item, so items can be scheduled
+/// to hit a desired retention. Grades are taken as `Quality`s, mapped with
+/// [`Quality::to_four_button`]. Same-day learning steps are not modelled.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fsrs {
+    config: FsrsConfig,
+}
+
+impl Fsrs {
+    /// Return a `Fsrs` with the given settings.
+    pub fn new(config: FsrsConfig) -> Self {
+        Self { config }
+    }
+
+    /// Get the settings of this scheduler.
+    pub fn config(&self) -> &FsrsConfig {
+        &self.config
+    }
+
+    /// Returns the new memory state after a review with the given quality, `elapsed_days` days
+    /// after the previous review. `state` is `None` for an item that has never been reviewed.
+    pub fn review(
+        &self,
+        state: Option<&FsrsState>,
+        quality: Quality,
+        elapsed_days: f64,
+    ) -> FsrsState {
+        let rating = Self::rating(quality);
+        let state = match state {
+            Some(state) => state,
+            None => {
+                return FsrsState::new(
+                    self.w(rating as usize - 1).max(0.1),
+                    self.initial_difficulty(rating),
+                )
+            }
+        };
+
+        let retrievability = state.retrievability(elapsed_days);
+        let stability = if rating == 1 {
+            self.forget_stability(state, retrievability)
+        } else {
+            self.recall_stability(state, retrievability, rating)
+        };
+
+        FsrsState::new(stability, self.next_difficulty(state.difficulty, rating))
+    }
+
+    /// Returns the interval in days after which the probability of recall drops to the desired
+    /// retention.
+    pub fn interval(&self, state: &FsrsState) -> u32 {
+        let interval =
+            state.stability / FACTOR * (self.config.desired_retention.powf(1.0 / DECAY) - 1.0);
+        (interval.round().max(1.0) as u32).min(self.config.maximum_interval)
+    }
+
+    fn w(&self, i: usize) -> f64 {
+        self.config.weights[i]
+    }
+
+    /// Map a quality to FSRS's ratings: 1 (again), 2 (hard), 3 (good) and 4 (easy).
+    fn rating(quality: Quality) -> u8 {
+        match quality.to_four_button() {
+            FourButton::Again => 1,
+            FourButton::Hard => 2,
+            FourButton::Good => 3,
+            FourButton::Easy => 4,
+        }
+    }
+
+    fn initial_difficulty(&self, rating: u8) -> f64 {
+        (self.w(4) - (rating as f64 - 3.0) * self.w(5)).clamp(1.0, 10.0)
+    }
+
+    fn next_difficulty(&self, difficulty: f64, rating: u8) -> f64 {
+        let next = difficulty - self.w(6) * (rating as f64 - 3.0);
+        // Mean reversion towards the initial difficulty of a `Good` rating.
+        (self.w(7) * self.w(4) + (1.0 - self.w(7)) * next).clamp(1.0, 10.0)
+    }
+
+    fn recall_stability(&self, state: &FsrsState, retrievability: f64, rating: u8) -> f64 {
+        let hard_penalty = if rating == 2 { self.w(15) } else { 1.0 };
+        let easy_bonus = if rating == 4 { self.w(16) } else { 1.0 };
+
+        state.stability
+            * (1.0
+                + self.w(8).exp()
+                    * (11.0 - state.difficulty)
+                    * state.stability.powf(-self.w(9))
+                    * (((1.0 - retrievability) * self.w(10)).exp() - 1.0)
+                    * hard_penalty
+                    * easy_bonus)
+    }
+
+    fn forget_stability(&self, state: &FsrsState, retrievability: f64) -> f64 {
+        self.w(11)
+            * state.difficulty.powf(-self.w(12))
+            * ((state.stability + 1.0).powf(self.w(13)) - 1.0)
+            * ((1.0 - retrievability) * self.w(14)).exp()
+    }
+}
+
+impl Scheduler for Fsrs {
+    /// `None` for an item that has never been reviewed.
+    type State = Option<FsrsState>;
+
+    fn initial_state(&self) -> Option<FsrsState> {
+        None
+    }
+
+    fn review(
+        &self,
+        state: &Option<FsrsState>,
+        quality: Quality,
+        elapsed: Duration,
+    ) -> Self::State {
+        let elapsed_days = elapsed.as_secs_f64() / SECONDS_PER_DAY as f64;
+        Some(Fsrs::review(self, state.as_ref(), quality, elapsed_days))
+    }
+
+    fn interval(&self, state: &Option<FsrsState>) -> Duration {
+        days(
+            state
+                .as_ref()
+                .map_or(0, |state| Fsrs::interval(self, state)) as u64,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    // The expected values below are the outputs of the FSRS-4.5 reference formulas with the
+    // default weights.
+
+    #[test]
+    fn first_review() {
+        let fsrs = Fsrs::default();
+        let expected = [
+            (Quality::Blackout, 0.4872, 7.6214, 1),
+            (Quality::CorrectHard, 1.4003, 6.3916, 1),
+            (Quality::CorrectHesitant, 3.7145, 5.1618, 4),
+            (Quality::Perfect, 13.8206, 3.932, 14),
+        ];
+        for (quality, stability, difficulty, interval) in expected.iter() {
+            let state = fsrs.review(None, *quality, 0.0);
+            assert_close(state.stability(), *stability);
+            assert_close(state.difficulty(), *difficulty);
+            assert_eq!(fsrs.interval(&state), *interval);
+        }
+    }
+
+    #[test]
+    fn second_review() {
+        let fsrs = Fsrs::default();
+        let state = FsrsState::new(3.7145, 5.1618);
+        assert_close(state.retrievability(4.0), 0.8934995006528037);
+
+        let expected = [
+            (Quality::Incorrect, 1.4332344897795595, 6.901155, 1),
+            (Quality::CorrectHard, 6.234966035075983, 6.0314775, 6),
+            (Quality::CorrectHesitant, 14.808100506496405, 5.1618, 15),
+            (Quality::Perfect, 35.61414825643041, 4.2921225, 36),
+        ];
+        for (quality, stability, difficulty, interval) in expected.iter() {
+            let next = fsrs.review(Some(&state), *quality, 4.0);
+            assert_close(next.stability(), *stability);
+            assert_close(next.difficulty(), *difficulty);
+            assert_eq!(fsrs.interval(&next), *interval);
+        }
+    }
+
+    #[test]
+    fn desired_retention() {
+        let fsrs = Fsrs::new(FsrsConfig {
+            desired_retention: 0.8,
+            ..FsrsConfig::default()
+        });
+        let state = FsrsState::new(14.808100506496405, 5.1618);
+        assert_eq!(fsrs.interval(&state), 36);
+        assert_close(
+            state.retrievability(fsrs.interval(&state) as f64),
+            0.7980212028723266,
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use std::ops::RangeInclusive;
+
+use crate::rng::below;
+use crate::RandomSource;
+
+/// Returns the range of days an interval may be fuzzed to, as Anki does.
+///
+/// The range grows with the interval: ±25% below a week, ±15% (at least 2 days) below a month
+/// and ±5% (at least 4 days) beyond that. Intervals of a day or less are never fuzzed.
+pub fn fuzz_range(interval: usize) -> RangeInclusive<usize> {
+    let fuzz = match interval {
+        0 | 1 => return interval..=interval,
+        2 => return 2..=3,
+        3..=6 => interval / 4,
+        7..=29 => (interval * 15 / 100).max(2),
+        _ => (interval / 20).max(4),
+    }
+    .max(1);
+
+    interval - fuzz..=interval.saturating_add(fuzz)
+}
+
+/// Returns an interval picked uniformly at random from [`fuzz_range`].
+pub fn fuzz_interval<R: RandomSource + ?Sized>(interval: usize, rng: &mut R) -> usize {
+    let range = fuzz_range(interval);
+    let width = (range.end() - range.start()) as u64 + 1;
+    range.start() + below(rng, width) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SplitMix64;
+
+    #[test]
+    fn ranges_grow_with_interval() {
+        assert_eq!(fuzz_range(0), 0..=0);
+        assert_eq!(fuzz_range(1), 1..=1);
+        assert_eq!(fuzz_range(2), 2..=3);
+        assert_eq!(fuzz_range(4), 3..=5);
+        assert_eq!(fuzz_range(10), 8..=12);
+        assert_eq!(fuzz_range(20), 17..=23);
+        assert_eq!(fuzz_range(50), 46..=54);
+        assert_eq!(fuzz_range(200), 190..=210);
+        assert_eq!(
+            fuzz_range(usize::MAX),
+            usize::MAX - usize::MAX / 20..=usize::MAX
+        );
+    }
+
+    #[test]
+    fn fuzz_is_reproducible_and_in_range() {
+        let mut a = SplitMix64::new(42);
+        let mut b = SplitMix64::new(42);
+        let mut seen = Vec::new();
+        for _ in 0..100 {
+            let interval = fuzz_interval(20, &mut a);
+            assert_eq!(interval, fuzz_interval(20, &mut b));
+            assert!(fuzz_range(20).contains(&interval));
+            seen.push(interval);
+        }
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen, (17..=23).collect::<Vec<_>>());
+    }
+}
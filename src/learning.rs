@@ -0,0 +1,342 @@
+use std::time::Duration;
+
+use crate::scheduler::days;
+use crate::{Item, Quality, Scheduler, SchedulingMode, Sm2Config};
+
+/// The parameters of the learning phase that new and lapsed items go through before they are
+/// scheduled in whole days by SM-2.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct LearningConfig {
+    /// The intervals between reviews of a new item, before it graduates.
+    pub learning_steps: Vec<Duration>,
+    /// The intervals between reviews of a lapsed item, before it returns to its SM-2 schedule.
+    pub relearning_steps: Vec<Duration>,
+    /// The interval in days given to an item that graduates by passing its last learning step.
+    pub graduating_interval: usize,
+    /// The interval in days given to an item that graduates early with a perfect response.
+    pub easy_interval: usize,
+}
+
+impl Default for LearningConfig {
+    /// Return Anki's default steps: 1 and 10 minutes for learning, 10 minutes for relearning,
+    /// graduating to 1 day, or 4 days for a perfect response.
+    fn default() -> Self {
+        Self {
+            learning_steps: vec![Duration::from_secs(60), Duration::from_secs(10 * 60)],
+            relearning_steps: vec![Duration::from_secs(10 * 60)],
+            graduating_interval: 1,
+            easy_interval: 4,
+        }
+    }
+}
+
+/// Which phase a `LearningItem` is in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LearningPhase {
+    /// A new item at the given learning step, counted from 0.
+    Learning { step: usize },
+    /// A lapsed item at the given relearning step, counted from 0.
+    Relearning { step: usize },
+    /// An item that has graduated and is scheduled in days by SM-2.
+    Review,
+}
+
+/// An `Item` together with its learning phase and the time until it is next due.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LearningItem {
+    item: Item,
+    phase: LearningPhase,
+    interval: Duration,
+}
+
+impl LearningItem {
+    /// Return a `LearningItem` for an `Item` that has already graduated, e.g. one that was
+    /// reviewed before learning steps were used.
+    pub fn graduated(item: Item, config: &Sm2Config) -> Self {
+        Self {
+            item,
+            phase: LearningPhase::Review,
+            interval: days(item.interval_with_config(config) as u64),
+        }
+    }
+
+    /// Get the underlying `Item`.
+    pub fn item(&self) -> &Item {
+        &self.item
+    }
+
+    /// Get the learning phase of the item.
+    pub fn phase(&self) -> LearningPhase {
+        self.phase
+    }
+
+    /// Returns whether the item is still in learning or relearning.
+    pub fn is_learning(&self) -> bool {
+        self.phase != LearningPhase::Review
+    }
+
+    /// Get the time from the most recent review until the item is next due.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+/// SM-2 with sub-day learning and relearning steps in front of it.
+///
+/// A new item is shown after each learning step in turn. Failing a step starts the steps over,
+/// and passing the last one graduates the item with the graduating interval. A perfect response
+/// graduates it straight away with the easy interval. Once graduated, the item is reviewed with
+/// SM-2, and a failed review sends it through the relearning steps before it returns to its SM-2
+/// schedule. If there are no steps, items skip the corresponding phase.
+///
+/// The graduation interval is stored as the last interval of the `Item`, and every later interval
+/// is the previous one multiplied by the E-factor. Items are therefore always scheduled with
+/// `SchedulingMode::Recurrence`, whatever the mode of the SM-2 parameters.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LearningSm2 {
+    learning: LearningConfig,
+    sm2: Sm2Config,
+}
+
+impl LearningSm2 {
+    /// Return a `LearningSm2` scheduler with the given parameters.
+    pub fn new(learning: LearningConfig, sm2: Sm2Config) -> Self {
+        Self { learning, sm2 }
+    }
+
+    /// Get the learning phase parameters of this scheduler.
+    pub fn learning_config(&self) -> &LearningConfig {
+        &self.learning
+    }
+
+    /// Get the SM-2 parameters of this scheduler.
+    pub fn sm2_config(&self) -> &Sm2Config {
+        &self.sm2
+    }
+
+    /// Get the SM-2 parameters used to calculate intervals, which always use
+    /// `SchedulingMode::Recurrence`.
+    pub fn schedule_config(&self) -> Sm2Config {
+        Sm2Config {
+            mode: SchedulingMode::Recurrence,
+            ..self.sm2
+        }
+    }
+
+    /// Returns the `Item` after grading it, with the interval after graduation, `first_interval`,
+    /// carried on to the next SM-2 step.
+    fn grade(&self, item: &Item, quality: Quality, first_interval: usize) -> Item {
+        let efactor = self.sm2.next_efactor(item.efactor(), quality);
+        let config = Sm2Config {
+            first_interval,
+            second_interval: (item.last_interval() as f64 * efactor).ceil() as usize,
+            ..self.schedule_config()
+        };
+        item.grade_with_config(quality, &config)
+    }
+
+    /// Return a `LearningItem` for an item that has never been reviewed.
+    pub fn new_item(&self) -> LearningItem {
+        LearningItem {
+            item: self.sm2.new_item(),
+            phase: LearningPhase::Learning { step: 0 },
+            interval: Duration::from_secs(0),
+        }
+    }
+
+    /// Returns a new `LearningItem` after reviewing `item` with the given quality.
+    pub fn review(&self, item: &LearningItem, quality: Quality) -> LearningItem {
+        let config = self.schedule_config();
+        match item.phase {
+            LearningPhase::Learning { step } => {
+                let steps = &self.learning.learning_steps;
+                match self.next_step(steps, quality, step) {
+                    Some(step) => LearningItem {
+                        item: item.item,
+                        phase: LearningPhase::Learning { step },
+                        interval: steps[step],
+                    },
+                    None => {
+                        let interval = if quality == Quality::Perfect {
+                            self.learning.easy_interval
+                        } else if self.sm2.is_pass(quality) {
+                            self.learning.graduating_interval
+                        } else {
+                            // Failed without any learning steps to repeat.
+                            self.sm2.first_interval
+                        };
+                        LearningItem::graduated(self.grade(&item.item, quality, interval), &config)
+                    }
+                }
+            }
+            LearningPhase::Relearning { step } => {
+                let steps = &self.learning.relearning_steps;
+                match self.next_step(steps, quality, step) {
+                    Some(step) => LearningItem {
+                        item: item.item,
+                        phase: LearningPhase::Relearning { step },
+                        interval: steps[step],
+                    },
+                    None => LearningItem::graduated(item.item, &config),
+                }
+            }
+            LearningPhase::Review => {
+                let reviewed = self.grade(&item.item, quality, self.sm2.first_interval);
+                match self.learning.relearning_steps.first() {
+                    Some(&interval) if !self.sm2.is_pass(quality) => LearningItem {
+                        item: reviewed,
+                        phase: LearningPhase::Relearning { step: 0 },
+                        interval,
+                    },
+                    _ => LearningItem::graduated(reviewed, &config),
+                }
+            }
+        }
+    }
+
+    /// Returns the step an item that has not graduated moves to: the next one if it passed, or
+    /// the first one if it failed. Returns `None` if the item graduates instead, either because
+    /// it passed the last step or was perfect, or because there are no steps.
+    fn next_step(&self, steps: &[Duration], quality: Quality, step: usize) -> Option<usize> {
+        let next = if quality == Quality::Perfect {
+            return None;
+        } else if self.sm2.is_pass(quality) {
+            step + 1
+        } else {
+            0
+        };
+        if next < steps.len() {
+            Some(next)
+        } else {
+            None
+        }
+    }
+}
+
+impl Scheduler for LearningSm2 {
+    type State = LearningItem;
+
+    fn initial_state(&self) -> LearningItem {
+        self.new_item()
+    }
+
+    fn review(&self, state: &LearningItem, quality: Quality, _elapsed: Duration) -> LearningItem {
+        LearningSm2::review(self, state, quality)
+    }
+
+    fn interval(&self, state: &LearningItem) -> Duration {
+        state.interval()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn new_item_steps_then_graduates() {
+        let scheduler = LearningSm2::default();
+        let item = scheduler.new_item();
+
+        let item = scheduler.review(&item, Quality::CorrectHesitant);
+        assert_eq!(item.phase(), LearningPhase::Learning { step: 1 });
+        assert_eq!(item.interval(), minutes(10));
+
+        let failed = scheduler.review(&item, Quality::Incorrect);
+        assert_eq!(failed.phase(), LearningPhase::Learning { step: 0 });
+        assert_eq!(failed.interval(), minutes(1));
+
+        let item = scheduler.review(&item, Quality::CorrectHesitant);
+        assert_eq!(item.phase(), LearningPhase::Review);
+        assert_eq!(item.interval(), days(1));
+        assert_eq!(item.item().repetitions(), 1);
+
+        // Later intervals build on the graduating interval: 1 * 2.5 = 3.
+        let item = scheduler.review(&item, Quality::CorrectHesitant);
+        assert_eq!(item.interval(), days(3));
+    }
+
+    #[test]
+    fn item_agrees_with_graduating_interval() {
+        let scheduler = LearningSm2::new(
+            LearningConfig {
+                graduating_interval: 3,
+                ..LearningConfig::default()
+            },
+            Sm2Config::default(),
+        );
+        let mut item = scheduler.new_item();
+        for _ in 0..2 {
+            item = scheduler.review(&item, Quality::CorrectHesitant);
+        }
+        assert_eq!(item.interval(), days(3));
+        assert_eq!(item.item().last_interval(), 3);
+        let config = scheduler.schedule_config();
+        assert_eq!(item.item().interval_with_config(&config), 3);
+
+        let item = scheduler.review(&item, Quality::CorrectHesitant);
+        assert_eq!(item.interval(), days(8));
+        assert_eq!(item.item().interval_with_config(&config), 8);
+    }
+
+    #[test]
+    fn perfect_response_graduates_with_easy_interval() {
+        let scheduler = LearningSm2::default();
+        let item = scheduler.review(&scheduler.new_item(), Quality::Perfect);
+        assert!(!item.is_learning());
+        assert_eq!(item.interval(), days(4));
+    }
+
+    #[test]
+    fn lapse_goes_through_relearning() {
+        let scheduler = LearningSm2::new(
+            LearningConfig {
+                relearning_steps: vec![minutes(10), minutes(60)],
+                ..LearningConfig::default()
+            },
+            Sm2Config::default(),
+        );
+        let item = LearningItem::graduated(Item::new(3, 2.5), scheduler.sm2_config());
+        assert_eq!(item.interval(), days(15));
+
+        let item = scheduler.review(&item, Quality::Blackout);
+        assert_eq!(item.phase(), LearningPhase::Relearning { step: 0 });
+        assert_eq!(item.interval(), minutes(10));
+        let item = scheduler.review(&item, Quality::CorrectHard);
+        assert_eq!(item.interval(), minutes(60));
+        let item = scheduler.review(&item, Quality::CorrectHard);
+        assert_eq!(item.phase(), LearningPhase::Review);
+        assert_eq!(item.interval(), days(1));
+    }
+
+    #[test]
+    fn no_steps_skips_learning() {
+        let scheduler = LearningSm2::new(
+            LearningConfig {
+                learning_steps: Vec::new(),
+                relearning_steps: Vec::new(),
+                ..LearningConfig::default()
+            },
+            Sm2Config::default(),
+        );
+        let failed = scheduler.review(&scheduler.new_item(), Quality::Incorrect);
+        assert_eq!(failed.phase(), LearningPhase::Review);
+        assert_eq!(failed.interval(), days(1));
+        assert_eq!(failed.item().repetitions(), 1);
+
+        let item = scheduler.review(&scheduler.new_item(), Quality::CorrectHard);
+        assert_eq!(item.phase(), LearningPhase::Review);
+        let item = scheduler.review(&item, Quality::Blackout);
+        assert_eq!(item.phase(), LearningPhase::Review);
+        assert_eq!(item.interval(), days(1));
+    }
+}
@@ -0,0 +1,209 @@
+use std::time::Duration;
+
+use crate::scheduler::days;
+use crate::{Error, Quality, Scheduler};
+
+/// Where a card goes in a `Leitner` system when it is answered incorrectly.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Demotion {
+    /// Back to the first box.
+    FirstBox,
+    /// Down to the previous box.
+    OneBoxDown,
+}
+
+/// The box a card is in under a `Leitner` system, numbered from 1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "usize", into = "usize"))]
+pub struct LeitnerBox(usize);
+
+impl From<usize> for LeitnerBox {
+    fn from(number: usize) -> Self {
+        Self::new(number)
+    }
+}
+
+impl From<LeitnerBox> for usize {
+    fn from(current: LeitnerBox) -> Self {
+        current.number()
+    }
+}
+
+impl LeitnerBox {
+    /// Return the box with the given number. Box numbers below 1 are treated as 1.
+    pub fn new(number: usize) -> Self {
+        Self(number.max(1))
+    }
+
+    /// Get the number of this box, from 1.
+    pub fn number(&self) -> usize {
+        self.0
+    }
+}
+
+impl Default for LeitnerBox {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+/// The Leitner box system.
+///
+/// Every box has a fixed interval. A correct answer (a quality of 3 or above, as with
+/// `Item::review`) promotes the card to the next box, and an incorrect answer demotes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "LeitnerRepr"))]
+pub struct Leitner {
+    /// The interval in days of each box.
+    intervals: Vec<u32>,
+    demotion: Demotion,
+}
+
+/// The stored representation of a `Leitner` system, validated when deserialized.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct LeitnerRepr {
+    intervals: Vec<u32>,
+    demotion: Demotion,
+}
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<LeitnerRepr> for Leitner {
+    type Error = Error;
+
+    fn try_from(repr: LeitnerRepr) -> Result<Self, Self::Error> {
+        Self::try_new(repr.intervals, repr.demotion)
+    }
+}
+
+impl Default for Leitner {
+    /// Return a `Leitner` system with 5 boxes of 1, 2, 4, 8 and 16 days that demotes incorrect
+    /// answers to the first box.
+    fn default() -> Self {
+        Self::new(vec![1, 2, 4, 8, 16], Demotion::FirstBox)
+    }
+}
+
+impl Leitner {
+    /// Return a `Leitner` system with one box per interval, given in days.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `intervals` is empty.
+    pub fn new(intervals: Vec<u32>, demotion: Demotion) -> Self {
+        match Self::try_new(intervals, demotion) {
+            Ok(leitner) => leitner,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Return a `Leitner` system with one box per interval, given in days.
+    /// If `intervals` is empty, this will return an `Err`.
+    pub fn try_new(intervals: Vec<u32>, demotion: Demotion) -> Result<Self, Error> {
+        if intervals.is_empty() {
+            return Err(Error::NoLeitnerBoxesError);
+        }
+        Ok(Self {
+            intervals,
+            demotion,
+        })
+    }
+
+    /// Get the interval in days of each box.
+    pub fn intervals(&self) -> &[u32] {
+        &self.intervals
+    }
+
+    /// Returns the box a card moves to from `current` after a review with the given quality.
+    pub fn review(&self, current: LeitnerBox, quality: Quality) -> LeitnerBox {
+        let last = self.intervals.len();
+        let number = current.number().min(last);
+        if quality.is_pass() {
+            LeitnerBox::new((number + 1).min(last))
+        } else {
+            match self.demotion {
+                Demotion::FirstBox => LeitnerBox::new(1),
+                Demotion::OneBoxDown => LeitnerBox::new(number.saturating_sub(1)),
+            }
+        }
+    }
+
+    /// Returns the interval in days of the given box.
+    /// Boxes past the last one use the interval of the last box.
+    pub fn interval(&self, current: LeitnerBox) -> u32 {
+        let index = current.number().min(self.intervals.len()).saturating_sub(1);
+        self.intervals[index]
+    }
+}
+
+impl Scheduler for Leitner {
+    type State = LeitnerBox;
+
+    fn initial_state(&self) -> LeitnerBox {
+        LeitnerBox::default()
+    }
+
+    fn review(&self, state: &LeitnerBox, quality: Quality, _elapsed: Duration) -> LeitnerBox {
+        Leitner::review(self, *state, quality)
+    }
+
+    fn interval(&self, state: &LeitnerBox) -> Duration {
+        days(Leitner::interval(self, *state) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn promotion_stops_at_last_box() {
+        let leitner = Leitner::new(vec![1, 3, 7], Demotion::FirstBox);
+        let mut current = LeitnerBox::default();
+        let mut intervals = Vec::new();
+        for _ in 0..4 {
+            current = leitner.review(current, Quality::CorrectHard);
+            intervals.push(leitner.interval(current));
+        }
+        assert_eq!(intervals, [3, 7, 7, 7]);
+    }
+
+    #[test]
+    fn demotion() {
+        let first = Leitner::new(vec![1, 3, 7], Demotion::FirstBox);
+        let down = Leitner::new(vec![1, 3, 7], Demotion::OneBoxDown);
+        let current = LeitnerBox::new(3);
+        assert_eq!(first.review(current, Quality::IncorrectEasy).number(), 1);
+        assert_eq!(down.review(current, Quality::IncorrectEasy).number(), 2);
+        assert_eq!(
+            down.review(LeitnerBox::new(1), Quality::Blackout).number(),
+            1
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn no_boxes_panics() {
+        Leitner::new(Vec::new(), Demotion::FirstBox);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserializing_keeps_invariants() {
+        let current: LeitnerBox = serde_json::from_str("0").unwrap();
+        assert_eq!(current, LeitnerBox::new(1));
+        assert_eq!(serde_json::to_string(&current).unwrap(), "1");
+
+        let down = Leitner::new(vec![1, 3, 7], Demotion::OneBoxDown);
+        assert_eq!(down.review(current, Quality::Blackout).number(), 1);
+        assert_eq!(down.interval(current), 1);
+
+        let json = r#"{"intervals":[],"demotion":"FirstBox"}"#;
+        assert!(serde_json::from_str::<Leitner>(json).is_err());
+        let json = serde_json::to_string(&down).unwrap();
+        assert_eq!(serde_json::from_str::<Leitner>(&json).unwrap(), down);
+    }
+}
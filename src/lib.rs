@@ -22,17 +22,73 @@ pub fn main() {
     assert_eq!(interval, 15);
 }
 ```
+
+# Features
+
+- `chrono` - allows scheduling items against `chrono::NaiveDate` instead of plain `u64` day numbers.
+- `serde` - implements `Serialize` and `Deserialize` for the crate's types.
+  `Item`s are stored with a format version and are validated when deserialized.
 */
 
+mod anki;
+mod card;
+mod config;
+mod deck;
+mod fsrs;
+mod fuzz;
+mod learning;
+mod leitner;
+mod quality;
+#[cfg(feature = "serde")]
+mod repr;
+mod rng;
+mod schedule;
+mod scheduler;
+mod session;
+mod sm5;
+mod stats;
+
+pub use anki::{AnkiCard, AnkiConfig, AnkiSm2, ANKI_MINIMUM_EASE};
+pub use card::{Card, CardState, ReviewLog, DEFAULT_UNDO_DEPTH};
+pub use config::{LeechAction, Sm2Config};
+pub use deck::Deck;
+pub use fsrs::{Fsrs, FsrsConfig, FsrsState, FSRS_DEFAULT_WEIGHTS};
+pub use fuzz::{fuzz_interval, fuzz_range};
+pub use learning::{LearningConfig, LearningItem, LearningPhase, LearningSm2};
+pub use leitner::{Demotion, Leitner, LeitnerBox};
+pub use quality::{FourButton, Quality, TwoButton};
+pub use rng::{RandomSource, SplitMix64};
+pub use schedule::{Date, Scheduled};
+pub use scheduler::{Scheduler, Sm2};
+pub use session::{QueueOrder, Session, SessionConfig};
+pub use sm5::{OfMatrix, Sm5, Sm5Item, OF_MATRIX_EFACTORS, OF_MATRIX_REPETITIONS};
+pub use stats::{Retention, StateCounts, Stats, MATURE_INTERVAL};
+
+use std::convert::TryFrom;
 use std::default::Default;
 use std::error::Error as StdError;
 use std::fmt;
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Error {
     /// The maximum value for the quality of a review is 5.
     /// This error is for when a quality above 5 is given.
     QualityAboveFiveError(u8),
+    /// This error is for when an item is looked up by a key that does not exist.
+    ItemNotFoundError,
+    /// This error is for when a string cannot be parsed as a `Quality`.
+    ParseQualityError,
+    /// This error is for when an E-factor is NaN.
+    NanEFactorError,
+    /// This error is for when an E-factor is infinite.
+    InfiniteEFactorError(f64),
+    /// This error is for when an E-factor is negative.
+    NegativeEFactorError(f64),
+    /// This error is for when a Leitner system is given no boxes.
+    NoLeitnerBoxesError,
+    /// This error is for when a card is reviewed while it is suspended or buried.
+    CardUnavailableError,
 }
 
 impl fmt::Display for Error {
@@ -41,19 +97,76 @@ impl fmt::Display for Error {
             Error::QualityAboveFiveError(q) => {
                 write!(f, "Quality cannot be greater than 5, {} was given.", q)
             }
+            Error::ItemNotFoundError => write!(f, "No item exists with the given key."),
+            Error::ParseQualityError => write!(
+                f,
+                "Quality must be a number from 0 to 5 or the name of a quality."
+            ),
+            Error::NanEFactorError => write!(f, "E-factor cannot be NaN."),
+            Error::InfiniteEFactorError(ef) => {
+                write!(f, "E-factor cannot be infinite, {} was given.", ef)
+            }
+            Error::NegativeEFactorError(ef) => {
+                write!(f, "E-factor cannot be negative, {} was given.", ef)
+            }
+            Error::NoLeitnerBoxesError => write!(f, "A Leitner system needs at least one box."),
+            Error::CardUnavailableError => {
+                write!(f, "A suspended or buried card cannot be reviewed.")
+            }
         }
     }
 }
 
 impl StdError for Error {}
 
+/// How the interval of an `Item` is derived from its review history.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SchedulingMode {
+    /// I(n) = 6 * EF^(n-2), using the current E-factor for every step.
+    /// This is the default, and is what `Item::interval` uses.
+    #[default]
+    ClosedForm,
+    /// I(n) = I(n-1) * EF, using the E-factor in effect at each review.
+    /// This reproduces the schedules of the published SM-2 algorithm.
+    Recurrence,
+}
+
+/// How a review before the due date is graded by `Scheduled::review_at_with_config`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EarlyReviewPolicy {
+    /// Grade the review as if the item were due. This is the default.
+    #[default]
+    Full,
+    /// On a passing grade, keep the repetitions and scale the E-factor change by the fraction of
+    /// the interval that has elapsed, then schedule the item again from the day of the review.
+    /// Failing grades are graded in full.
+    Damped,
+    /// Leave the schedule untouched and only drill the item. See [`Item::drill`].
+    Ignore,
+}
+
 /// A struct that holds the essential metadata for an item using the supermemo2 algorithm.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(into = "repr::ItemRepr", try_from = "repr::ItemRepr")
+)]
 pub struct Item {
     /// The number of reviews of this item.
     repetitions: usize,
     /// Easiness factor.
     efactor: f64,
+    /// The interval calculated by the SM-2 recurrence at the most recent review.
+    last_interval: usize,
+    /// Whether the item was answered with a quality below 4 and must be drilled again today.
+    needs_drill: bool,
+    /// The number of times the item was forgotten after it had been reviewed successfully.
+    lapses: usize,
+    /// Whether the most recent review was failed, so that failing again is not another lapse.
+    last_failed: bool,
 }
 
 impl Default for Item {
@@ -62,19 +175,50 @@ impl Default for Item {
         Self {
             repetitions: 0,
             efactor: 2.5,
+            last_interval: 0,
+            needs_drill: false,
+            lapses: 0,
+            last_failed: false,
         }
     }
 }
 
 impl Item {
     /// Return an `Item` with the given number of repetitions and E-factor.
+    /// The last interval is initialised from the closed-form formula.
+    /// The E-factor is not validated; see [`Item::try_new`].
     pub fn new(repetitions: usize, efactor: f64) -> Self {
-        Self {
+        let item = Self {
             repetitions,
             efactor,
+            last_interval: 0,
+            needs_drill: false,
+            lapses: 0,
+            last_failed: false,
+        };
+
+        item.with_last_interval(item.interval())
+    }
+
+    /// Return an `Item` with the given number of repetitions and E-factor.
+    /// If the E-factor is NaN, infinite or negative, this will return an `Err`.
+    pub fn try_new(repetitions: usize, efactor: f64) -> Result<Self, Error> {
+        Ok(Self::new(repetitions, validate_efactor(efactor)?))
+    }
+
+    /// Return this `Item` with the given last interval, e.g. when restoring a stored `Item`.
+    pub fn with_last_interval(self, last_interval: usize) -> Self {
+        Self {
+            last_interval,
+            ..self
         }
     }
 
+    /// Return this `Item` with the given number of lapses, e.g. to reset it after rewriting a leech.
+    pub fn with_lapses(self, lapses: usize) -> Self {
+        Self { lapses, ..self }
+    }
+
     /// Get the number of repetitions of this `Item`.
     pub fn repetitions(&self) -> usize {
         self.repetitions
@@ -85,38 +229,77 @@ impl Item {
         self.efactor
     }
 
+    /// Get the interval calculated by the SM-2 recurrence at the most recent review.
+    pub fn last_interval(&self) -> usize {
+        self.last_interval
+    }
+
+    /// Returns whether this `Item` was last answered with a quality below 4.
+    /// SM-2 prescribes repeating such items on the same day until they score at least 4.
+    pub fn needs_drill(&self) -> bool {
+        self.needs_drill
+    }
+
+    /// Get the number of times this `Item` was forgotten after it had been reviewed successfully.
+    /// Failing an item that has not been passed since it was last failed is not a lapse.
+    /// See [`Sm2Config::leech_threshold`].
+    pub fn lapses(&self) -> usize {
+        self.lapses
+    }
+
     /// Returns the current interval of the `Item`.
     /// The interval is defined as the time in days since the previous review after which
     /// this `Item` will be due for review.
     pub fn interval(&self) -> usize {
-        match self.repetitions {
-            0 => 0,
-            1 => 1,
-            2 => 6,
-            _ => (6.0 * self.efactor.powi(self.repetitions as i32 - 2)).ceil() as usize,
-        }
+        self.interval_with_config(&Sm2Config::default())
     }
 
-    fn new_efactor(&self, quality: u8) -> Result<f64, Error> {
-        let ef = if self.efactor < 1.3 {
-            1.3
-        } else {
-            self.efactor
+    /// Returns the current interval of the `Item` using the given `SchedulingMode`.
+    pub fn interval_with_mode(&self, mode: SchedulingMode) -> usize {
+        self.interval_with_config(&Sm2Config {
+            mode,
+            ..Sm2Config::default()
+        })
+    }
+
+    /// Returns the current interval of the `Item` using the given `Sm2Config`.
+    pub fn interval_with_config(&self, config: &Sm2Config) -> usize {
+        let interval = match config.mode {
+            SchedulingMode::ClosedForm => match self.repetitions {
+                0 => 0.0,
+                1 => config.first_interval as f64,
+                2 => config.second_interval as f64,
+                n => {
+                    let exponent = i32::try_from(n - 2).unwrap_or(i32::MAX);
+                    config.second_interval as f64 * self.efactor.powi(exponent)
+                }
+            },
+            SchedulingMode::Recurrence => self.last_interval as f64,
         };
 
-        if quality > 5 {
-            Err(Error::QualityAboveFiveError(quality))
-        } else {
-            // EF':=EF+(0.1-(5-q)*(0.08+(5-q)*0.02))
-            Ok(ef + (0.1 - (5.0 - quality as f64) * (0.08 + (5.0 - quality as f64) * 0.02)))
+        config.scale_interval(interval)
+    }
+
+    fn new_last_interval(
+        last_interval: f64,
+        repetitions: usize,
+        efactor: f64,
+        config: &Sm2Config,
+    ) -> usize {
+        match repetitions {
+            0 => 0,
+            1 => config.first_interval,
+            2 => config.second_interval,
+            // I(n):=I(n-1)*EF
+            _ => (last_interval * efactor).ceil() as usize,
         }
     }
 
-    fn new_repetitions(&self, quality: u8) -> Result<usize, Error> {
-        match quality {
-            0 | 1 | 2 => Ok(1),
-            3 | 4 | 5 => Ok(self.repetitions + 1),
-            _ => Err(Error::QualityAboveFiveError(quality)),
+    fn new_repetitions(&self, quality: Quality, config: &Sm2Config) -> usize {
+        if config.is_pass(quality) {
+            self.repetitions + 1
+        } else {
+            1
         }
     }
 
@@ -130,13 +313,133 @@ impl Item {
     /// - 4 - correct response after a hesitation
     /// - 5 - perfect response
     pub fn review(&self, quality: u8) -> Result<Self, Error> {
+        Ok(self.grade(Quality::try_from(quality)?))
+    }
+
+    /// Returns a new `Item` based on the given `Quality`.
+    /// This is the infallible version of [`Item::review`].
+    pub fn grade(&self, quality: Quality) -> Self {
+        self.grade_with_config(quality, &Sm2Config::default())
+    }
+
+    /// Returns a new `Item` based on the given quality, using the given `Sm2Config`.
+    /// If a quality above 5 is given, this will return an `Err`.
+    pub fn review_with_config(&self, quality: u8, config: &Sm2Config) -> Result<Self, Error> {
+        Ok(self.grade_with_config(Quality::try_from(quality)?, config))
+    }
+
+    /// Returns a new `Item` based on the given `Quality`, using the given `Sm2Config`.
+    pub fn grade_with_config(&self, quality: Quality, config: &Sm2Config) -> Self {
+        self.grade_late(quality, 0, config)
+    }
+
+    /// Returns a new `Item` based on the given quality, reviewed `elapsed_days` days after the
+    /// previous review.
+    /// If a quality above 5 is given, this will return an `Err`.
+    ///
+    /// See [`Item::grade_with_elapsed`]. Reviewing on time is the same as [`Item::review`], and
+    /// so is any review in the default `SchedulingMode::ClosedForm`.
+    pub fn review_with_elapsed(&self, quality: u8, elapsed_days: usize) -> Result<Self, Error> {
+        Ok(self.grade_with_elapsed(
+            Quality::try_from(quality)?,
+            elapsed_days,
+            &Sm2Config::default(),
+        ))
+    }
+
+    /// Returns a new `Item` based on the given `Quality`, reviewed `elapsed_days` days after the
+    /// previous review, using the given `Sm2Config`.
+    ///
+    /// In `SchedulingMode::Recurrence`, a passing grade after a late review is credited with the
+    /// days past the interval: the next interval grows from the time actually elapsed instead of
+    /// the scheduled interval. Failing grades and reviews that were not late are graded as with
+    /// [`Item::grade_with_config`].
+    /// The closed form depends solely on the repetitions and E-factor, so
+    /// `SchedulingMode::ClosedForm` ignores the elapsed time.
+    pub fn grade_with_elapsed(
+        &self,
+        quality: Quality,
+        elapsed_days: usize,
+        config: &Sm2Config,
+    ) -> Self {
+        let days_late = elapsed_days.saturating_sub(self.interval_with_config(config));
+        self.grade_late(quality, days_late, config)
+    }
+
+    /// Returns a new `Item` based on the given `Quality`, reviewed `days_late` days after it was
+    /// due. In `SchedulingMode::Recurrence`, the days late are credited to the last interval,
+    /// after undoing the interval multiplier, since they were counted after it was applied.
+    pub(crate) fn grade_late(
+        &self,
+        quality: Quality,
+        days_late: usize,
+        config: &Sm2Config,
+    ) -> Self {
+        let repetitions = self.new_repetitions(quality, config);
+        let efactor = config.next_efactor(self.efactor, quality);
+        let credit = if config.mode == SchedulingMode::Recurrence
+            && config.is_pass(quality)
+            && config.interval_multiplier > 0.0
+        {
+            days_late as f64 / config.interval_multiplier
+        } else {
+            0.0
+        };
+        let last_interval = self.last_interval as f64 + credit;
+        let failed = !config.is_pass(quality);
+        let lapsed = failed && self.repetitions > 0 && !self.last_failed;
+
+        Self {
+            repetitions,
+            efactor,
+            last_interval: Self::new_last_interval(last_interval, repetitions, efactor, config),
+            needs_drill: quality < Quality::CorrectHesitant,
+            lapses: self.lapses + lapsed as usize,
+            last_failed: failed,
+        }
+    }
+
+    /// Returns a new `Item` after a passing review early in its interval, with `fraction` of the
+    /// interval elapsed. The repetitions are kept and the E-factor moves `fraction` of the way to
+    /// its updated value. See [`EarlyReviewPolicy::Damped`].
+    pub(crate) fn grade_damped(&self, quality: Quality, fraction: f64, config: &Sm2Config) -> Self {
+        let efactor = config.next_efactor(self.efactor, quality);
+
+        Self {
+            efactor: self.efactor + (efactor - self.efactor) * fraction.clamp(0.0, 1.0),
+            needs_drill: quality < Quality::CorrectHesitant,
+            last_failed: false,
+            ..*self
+        }
+    }
+
+    /// Returns a new `Item` after repeating it on the same day as its review.
+    /// The repetitions and E-factor are left untouched; only whether the item still needs to be
+    /// drilled is updated.
+    /// If a quality above 5 is given, this will return an `Err`.
+    pub fn drill(&self, quality: u8) -> Result<Self, Error> {
+        let quality = Quality::try_from(quality)?;
+
         Ok(Self {
-            repetitions: self.new_repetitions(quality)?,
-            efactor: self.new_efactor(quality)?,
+            needs_drill: quality < Quality::CorrectHesitant,
+            ..*self
         })
     }
 }
 
+/// Returns the E-factor if it is a finite, non-negative number.
+pub(crate) fn validate_efactor(efactor: f64) -> Result<f64, Error> {
+    if efactor.is_nan() {
+        Err(Error::NanEFactorError)
+    } else if efactor.is_infinite() {
+        Err(Error::InfiniteEFactorError(efactor))
+    } else if efactor < 0.0 {
+        Err(Error::NegativeEFactorError(efactor))
+    } else {
+        Ok(efactor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,6 +458,24 @@ mod tests {
         item.review(6).unwrap();
     }
 
+    #[test]
+    fn try_new_validates_efactor() {
+        assert_eq!(Item::try_new(3, 2.4).unwrap(), Item::new(3, 2.4));
+        assert!(Item::try_new(0, 0.0).is_ok());
+        assert!(matches!(
+            Item::try_new(0, f64::NAN),
+            Err(Error::NanEFactorError)
+        ));
+        assert!(matches!(
+            Item::try_new(0, f64::INFINITY),
+            Err(Error::InfiniteEFactorError(_))
+        ));
+        assert!(matches!(
+            Item::try_new(0, -1.0),
+            Err(Error::NegativeEFactorError(_))
+        ));
+    }
+
     #[test]
     fn review_gives_correct_repetitions_and_efactor() {
         let item = Item::new(3, 2.4);
@@ -163,9 +484,143 @@ mod tests {
         assert_eq!(new_item.efactor, 2.5);
     }
 
+    #[test]
+    fn huge_intervals_saturate() {
+        assert_eq!(Item::new(2000, 2.5).interval(), usize::MAX);
+        assert_eq!(Item::new(usize::MAX, 2.5).interval(), usize::MAX);
+        assert_eq!(Item::new(usize::MAX, 0.5).interval(), 0);
+
+        let config = Sm2Config {
+            max_interval: Some(36500),
+            ..Sm2Config::default()
+        };
+        assert_eq!(Item::new(20, 2.5).interval_with_config(&config), 36500);
+        assert_eq!(Item::new(2000, 2.5).interval_with_config(&config), 36500);
+
+        let config = Sm2Config {
+            mode: SchedulingMode::Recurrence,
+            ..config
+        };
+        let mut item = Item::new(2000, 2.5);
+        for _ in 0..3 {
+            item = item.grade(Quality::Perfect);
+        }
+        assert_eq!(item.last_interval(), usize::MAX);
+        assert_eq!(item.interval_with_config(&config), 36500);
+    }
+
+    #[test]
+    fn grade_matches_review() {
+        let item = Item::new(3, 2.4);
+        let graded = item.grade(Quality::CorrectHard);
+        let reviewed = item.review(3).unwrap();
+        assert_eq!(graded.repetitions, reviewed.repetitions);
+        assert_eq!(graded.efactor, reviewed.efactor);
+    }
+
     #[test]
     fn calculate_interval() {
         let item = Item::new(5, 3.9);
         assert_eq!(item.interval(), 356);
     }
+
+    #[test]
+    fn recurrence_uses_efactor_at_each_step() {
+        let item = Item::default()
+            .review(5)
+            .unwrap()
+            .review(5)
+            .unwrap()
+            .review(5)
+            .unwrap()
+            .review(3)
+            .unwrap();
+        assert_eq!(item.interval_with_mode(SchedulingMode::Recurrence), 46);
+        assert_eq!(item.interval_with_mode(SchedulingMode::ClosedForm), 43);
+    }
+
+    #[test]
+    fn late_passing_review_is_credited() {
+        let config = Sm2Config {
+            mode: SchedulingMode::Recurrence,
+            ..Sm2Config::default()
+        };
+        let item = Item::new(3, 2.5);
+        assert_eq!(item.last_interval(), 15);
+
+        let good = Quality::CorrectHesitant;
+        let on_time = item.grade_with_config(good, &config);
+        assert_eq!(item.grade_with_elapsed(good, 15, &config), on_time);
+        assert_eq!(item.grade_with_elapsed(good, 10, &config), on_time);
+        assert_eq!(on_time.interval_with_config(&config), 38);
+
+        let late = item.grade_with_elapsed(good, 60, &config);
+        assert_eq!(late.interval_with_config(&config), 150);
+        assert_eq!(late.efactor(), on_time.efactor());
+
+        let failed = item.grade_with_elapsed(Quality::Incorrect, 60, &config);
+        assert_eq!(failed, item.review_with_config(1, &config).unwrap());
+
+        let doubled = Sm2Config {
+            interval_multiplier: 2.0,
+            ..config
+        };
+        let on_time = item.grade_with_elapsed(good, 30, &doubled);
+        assert_eq!(on_time.interval_with_config(&doubled), 76);
+        let late = item.grade_with_elapsed(good, 60, &doubled);
+        assert_eq!(late.interval_with_config(&doubled), 150);
+    }
+
+    #[test]
+    fn closed_form_ignores_elapsed_time() {
+        let item = Item::new(3, 2.5);
+        let late = item.review_with_elapsed(4, 60).unwrap();
+        assert_eq!(late, item.review(4).unwrap());
+        assert_eq!(late.last_interval(), late.interval());
+        assert!(item.review_with_elapsed(6, 60).is_err());
+    }
+
+    #[test]
+    fn failing_a_learned_item_is_a_lapse() {
+        let item = Item::default().review(1).unwrap().review(1).unwrap();
+        assert_eq!(item.lapses(), 0);
+        let item = item.review(0).unwrap().review(2).unwrap();
+        assert_eq!(item.lapses(), 0);
+        let item = item.review(4).unwrap().review(4).unwrap();
+        assert_eq!(item.lapses(), 0);
+        let item = item.review(2).unwrap().review(0).unwrap();
+        assert_eq!(item.lapses(), 1);
+        let item = item.review(3).unwrap().review(0).unwrap();
+        assert_eq!(item.lapses(), 2);
+        assert_eq!(item.drill(0).unwrap().lapses(), 2);
+        assert_eq!(item.with_lapses(0).lapses(), 0);
+    }
+
+    #[test]
+    fn new_initialises_last_interval_from_closed_form() {
+        let item = Item::new(5, 3.9);
+        assert_eq!(item.last_interval(), 356);
+        assert_eq!(item.review(4).unwrap().last_interval(), 1389);
+    }
+
+    #[test]
+    fn drill_until_quality_four() {
+        let item = Item::new(3, 2.4).review(3).unwrap();
+        assert!(item.needs_drill());
+
+        let drilled = item.drill(2).unwrap();
+        assert!(drilled.needs_drill());
+        let drilled = drilled.drill(4).unwrap();
+        assert!(!drilled.needs_drill());
+        assert_eq!(drilled.repetitions, item.repetitions);
+        assert_eq!(drilled.efactor, item.efactor);
+        assert_eq!(drilled.interval(), item.interval());
+    }
+
+    #[test]
+    #[should_panic]
+    fn drill_quality_above_5_returns_error() {
+        let item = Item::default();
+        item.drill(6).unwrap();
+    }
 }
@@ -0,0 +1,201 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Error;
+
+/// The quality of a response during a review, as graded by the learner.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Quality {
+    /// 0 - complete blackout.
+    Blackout = 0,
+    /// 1 - incorrect response; the correct one remembered.
+    Incorrect = 1,
+    /// 2 - incorrect response; where the correct one seemed easy to recall.
+    IncorrectEasy = 2,
+    /// 3 - correct response recalled with serious difficulty.
+    CorrectHard = 3,
+    /// 4 - correct response after a hesitation.
+    CorrectHesitant = 4,
+    /// 5 - perfect response.
+    Perfect = 5,
+}
+
+/// The answer buttons of a 4-button review UI, such as Anki's.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FourButton {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+/// The answer buttons of a 2-button review UI.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TwoButton {
+    Fail,
+    Pass,
+}
+
+impl Quality {
+    /// All qualities, from worst to best.
+    pub const ALL: [Quality; 6] = [
+        Quality::Blackout,
+        Quality::Incorrect,
+        Quality::IncorrectEasy,
+        Quality::CorrectHard,
+        Quality::CorrectHesitant,
+        Quality::Perfect,
+    ];
+
+    /// Get the numeric value of this quality, from 0 to 5.
+    pub fn value(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns whether this quality counts as a correct response, i.e. it is 3 or above.
+    pub fn is_pass(self) -> bool {
+        self >= Quality::CorrectHard
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Quality::Blackout => "Blackout",
+            Quality::Incorrect => "Incorrect",
+            Quality::IncorrectEasy => "IncorrectEasy",
+            Quality::CorrectHard => "CorrectHard",
+            Quality::CorrectHesitant => "CorrectHesitant",
+            Quality::Perfect => "Perfect",
+        }
+    }
+
+    /// Map this quality onto a 4-button UI.
+    /// All incorrect responses map to `Again`.
+    pub fn to_four_button(self) -> FourButton {
+        match self {
+            Quality::Blackout | Quality::Incorrect | Quality::IncorrectEasy => FourButton::Again,
+            Quality::CorrectHard => FourButton::Hard,
+            Quality::CorrectHesitant => FourButton::Good,
+            Quality::Perfect => FourButton::Easy,
+        }
+    }
+
+    /// Map this quality onto a 2-button UI.
+    pub fn to_two_button(self) -> TwoButton {
+        if self.is_pass() {
+            TwoButton::Pass
+        } else {
+            TwoButton::Fail
+        }
+    }
+}
+
+impl From<FourButton> for Quality {
+    fn from(button: FourButton) -> Self {
+        match button {
+            FourButton::Again => Quality::Incorrect,
+            FourButton::Hard => Quality::CorrectHard,
+            FourButton::Good => Quality::CorrectHesitant,
+            FourButton::Easy => Quality::Perfect,
+        }
+    }
+}
+
+impl From<TwoButton> for Quality {
+    fn from(button: TwoButton) -> Self {
+        match button {
+            TwoButton::Fail => Quality::Incorrect,
+            TwoButton::Pass => Quality::CorrectHesitant,
+        }
+    }
+}
+
+impl From<Quality> for u8 {
+    fn from(quality: Quality) -> Self {
+        quality.value()
+    }
+}
+
+impl TryFrom<u8> for Quality {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Quality::ALL
+            .get(value as usize)
+            .copied()
+            .ok_or(Error::QualityAboveFiveError(value))
+    }
+}
+
+impl FromStr for Quality {
+    type Err = Error;
+
+    /// Parse a quality from its numeric value ("0" to "5") or its name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Quality::ALL
+            .iter()
+            .copied()
+            .find(|quality| {
+                s == quality.value().to_string() || s.eq_ignore_ascii_case(quality.name())
+            })
+            .ok_or(Error::ParseQualityError)
+    }
+}
+
+impl fmt::Display for Quality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Quality::try_from(3).unwrap(), Quality::CorrectHard);
+        assert!(Quality::try_from(6).is_err());
+        assert_eq!(u8::from(Quality::Perfect), 5);
+        for quality in Quality::ALL.iter() {
+            assert_eq!(quality.to_string().parse::<Quality>().unwrap(), *quality);
+        }
+    }
+
+    #[test]
+    fn parse_numbers_and_names() {
+        assert_eq!("0".parse::<Quality>().unwrap(), Quality::Blackout);
+        assert_eq!(" 4 ".parse::<Quality>().unwrap(), Quality::CorrectHesitant);
+        assert_eq!(
+            "incorrecteasy".parse::<Quality>().unwrap(),
+            Quality::IncorrectEasy
+        );
+        assert!("6".parse::<Quality>().is_err());
+        assert!("great".parse::<Quality>().is_err());
+    }
+
+    #[test]
+    fn button_mappings() {
+        assert_eq!(Quality::IncorrectEasy.to_four_button(), FourButton::Again);
+        assert_eq!(Quality::from(FourButton::Good), Quality::CorrectHesitant);
+        assert_eq!(Quality::CorrectHard.to_two_button(), TwoButton::Pass);
+        assert_eq!(
+            Quality::from(TwoButton::Fail).to_two_button(),
+            TwoButton::Fail
+        );
+        for button in [
+            FourButton::Again,
+            FourButton::Hard,
+            FourButton::Good,
+            FourButton::Easy,
+        ]
+        .iter()
+        {
+            assert_eq!(Quality::from(*button).to_four_button(), *button);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{validate_efactor, Error, Item};
+
+/// The stored representation of an `Item`.
+/// It is tagged with a format version so that stored data keeps loading as fields are added.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "version")]
+pub(crate) enum ItemRepr {
+    #[serde(rename = "1")]
+    V1 {
+        repetitions: usize,
+        efactor: f64,
+        last_interval: usize,
+        #[serde(default)]
+        needs_drill: bool,
+        #[serde(default)]
+        lapses: usize,
+        #[serde(default)]
+        last_failed: bool,
+    },
+}
+
+impl From<Item> for ItemRepr {
+    fn from(item: Item) -> Self {
+        ItemRepr::V1 {
+            repetitions: item.repetitions,
+            efactor: item.efactor,
+            last_interval: item.last_interval,
+            needs_drill: item.needs_drill,
+            lapses: item.lapses,
+            last_failed: item.last_failed,
+        }
+    }
+}
+
+impl TryFrom<ItemRepr> for Item {
+    type Error = Error;
+
+    fn try_from(repr: ItemRepr) -> Result<Self, Self::Error> {
+        match repr {
+            ItemRepr::V1 {
+                repetitions,
+                efactor,
+                last_interval,
+                needs_drill,
+                lapses,
+                last_failed,
+            } => Ok(Item {
+                repetitions,
+                efactor: validate_efactor(efactor)?,
+                last_interval,
+                needs_drill,
+                lapses,
+                last_failed,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn item_round_trip() {
+        let item = Item::new(3, 2.4).review(5).unwrap();
+        let json = serde_json::to_string(&item).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":"1","repetitions":4,"efactor":2.5,"last_interval":38,"needs_drill":false,"lapses":0,"last_failed":false}"#
+        );
+
+        let restored: Item = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.repetitions(), 4);
+        assert_eq!(restored.efactor(), 2.5);
+        assert_eq!(restored.last_interval(), 38);
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let json = r#"{"version":"1","repetitions":4,"efactor":2.5,"last_interval":38}"#;
+        let restored: Item = serde_json::from_str(json).unwrap();
+        assert!(!restored.needs_drill());
+        assert_eq!(restored.lapses(), 0);
+    }
+
+    #[test]
+    fn negative_efactor_is_rejected() {
+        let json = r#"{"version":"1","repetitions":4,"efactor":-1.0,"last_interval":38}"#;
+        assert!(serde_json::from_str::<Item>(json).is_err());
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let json = r#"{"version":"0","repetitions":4,"efactor":2.5,"last_interval":38}"#;
+        assert!(serde_json::from_str::<Item>(json).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+/// A source of random numbers, e.g. for fuzzing intervals.
+/// Implement this for your own random number generator, or use `SplitMix64`.
+pub trait RandomSource {
+    /// Return the next random 64-bit number.
+    fn next_u64(&mut self) -> u64;
+}
+
+/// A small, seedable pseudo-random number generator (SplitMix64).
+/// Used where the crate needs reproducible randomness without pulling in a dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// Return a `SplitMix64` seeded with `seed`. The same seed always gives the same numbers.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Shuffle `items` in place using the Fisher-Yates algorithm.
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = below(self, i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+impl RandomSource for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Return a number in `0..bound`. `bound` must not be 0.
+pub(crate) fn below<R: RandomSource + ?Sized>(rng: &mut R, bound: u64) -> u64 {
+    rng.next_u64() % bound
+}
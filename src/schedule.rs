@@ -0,0 +1,468 @@
+use std::convert::TryFrom;
+
+use crate::{
+    fuzz_interval, fuzz_range, EarlyReviewPolicy, Error, Item, Quality, RandomSource, Sm2Config,
+};
+
+/// A calendar day that an `Item` can be scheduled against.
+///
+/// This is implemented for `u64` day numbers (days since any epoch of your choosing) and, with the
+/// `chrono` feature enabled, for `chrono::NaiveDate`.
+pub trait Date: Copy + Ord {
+    /// Return the date `days` days after this one, saturating at the latest representable date.
+    fn add_days(self, days: usize) -> Self;
+
+    /// Return the number of days from `earlier` to this date.
+    /// The result is negative if `earlier` is actually later than this date.
+    fn days_since(self, earlier: Self) -> i64;
+}
+
+impl Date for u64 {
+    fn add_days(self, days: usize) -> Self {
+        self.saturating_add(days as u64)
+    }
+
+    fn days_since(self, earlier: Self) -> i64 {
+        if self >= earlier {
+            (self - earlier).min(i64::MAX as u64) as i64
+        } else {
+            -((earlier - self).min(i64::MAX as u64) as i64)
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Date for chrono::NaiveDate {
+    fn add_days(self, days: usize) -> Self {
+        self.checked_add_days(chrono::Days::new(days as u64))
+            .unwrap_or(chrono::NaiveDate::MAX)
+    }
+
+    fn days_since(self, earlier: Self) -> i64 {
+        self.signed_duration_since(earlier).num_days()
+    }
+}
+
+/// An `Item` together with the date it was last reviewed and the date it is next due.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scheduled<D = u64> {
+    item: Item,
+    /// The date of the most recent review, if there has been one.
+    last_review: Option<D>,
+    /// The date on which the item is next due for review.
+    due: Option<D>,
+}
+
+impl<D> Default for Scheduled<D> {
+    /// Return a `Scheduled` wrapping a default `Item` that has never been reviewed.
+    fn default() -> Self {
+        Self::new(Item::default())
+    }
+}
+
+impl<D> From<Item> for Scheduled<D> {
+    fn from(item: Item) -> Self {
+        Self::new(item)
+    }
+}
+
+impl<D> Scheduled<D> {
+    /// Return a `Scheduled` wrapping an `Item` that has never been reviewed.
+    pub fn new(item: Item) -> Self {
+        Self {
+            item,
+            last_review: None,
+            due: None,
+        }
+    }
+
+    /// Get the underlying `Item`.
+    pub fn item(&self) -> &Item {
+        &self.item
+    }
+}
+
+impl<D: Date> Scheduled<D> {
+    /// Return a `Scheduled` for an `Item` that was last reviewed on `last_review`.
+    pub fn from_last_review(item: Item, last_review: D) -> Self {
+        Self::from_last_review_with_config(item, last_review, &Sm2Config::default())
+    }
+
+    /// Return a `Scheduled` for an `Item` that was last reviewed on `last_review`, using the
+    /// given `Sm2Config` to calculate the due date.
+    pub fn from_last_review_with_config(item: Item, last_review: D, config: &Sm2Config) -> Self {
+        Self {
+            item,
+            last_review: Some(last_review),
+            due: Some(last_review.add_days(item.interval_with_config(config))),
+        }
+    }
+
+    /// Get the date of the most recent review, or `None` if the item has never been reviewed.
+    pub fn last_review(&self) -> Option<D> {
+        self.last_review
+    }
+
+    /// Get the number of days from the most recent review to the due date, or `None` if the
+    /// item has never been reviewed.
+    pub fn scheduled_interval(&self) -> Option<usize> {
+        match (self.last_review, self.due) {
+            (Some(last_review), Some(due)) => Some(due.days_since(last_review).max(0) as usize),
+            _ => None,
+        }
+    }
+
+    /// Get the date on which the item is next due for review.
+    /// Items that have never been reviewed have no due date.
+    pub fn due_date(&self) -> Option<D> {
+        self.due
+    }
+
+    /// Returns whether the item should be reviewed on `now`.
+    /// Items that have never been reviewed are always due.
+    pub fn is_due(&self, now: D) -> bool {
+        self.due.is_none_or(|due| due <= now)
+    }
+
+    /// Returns whether a review on `now` would be before the due date.
+    /// Items that have never been reviewed are never early.
+    pub fn is_early(&self, now: D) -> bool {
+        self.due.is_some_and(|due| now < due)
+    }
+
+    /// Returns whether a review on `now` would be early and ignored by the `EarlyReviewPolicy`
+    /// of `config`, so that it only drills the item.
+    pub fn ignores_review(&self, now: D, config: &Sm2Config) -> bool {
+        config.early_review == EarlyReviewPolicy::Ignore && self.is_early(now)
+    }
+
+    /// Returns the number of days that `now` is past the due date.
+    /// This is negative if the item is not yet due, and 0 for items that have never been reviewed.
+    pub fn days_overdue(&self, now: D) -> i64 {
+        self.due.map_or(0, |due| now.days_since(due))
+    }
+
+    /// Returns a new `Scheduled` after reviewing the item with the given quality on `now`.
+    /// See [`Item::review`] for the meaning of the quality.
+    pub fn review_at(&self, quality: u8, now: D) -> Result<Self, Error> {
+        self.review_at_with_config(quality, now, &Sm2Config::default())
+    }
+
+    /// Returns a new `Scheduled` after reviewing the item with the given quality on `now`,
+    /// using the given `Sm2Config` for both the review and the new due date.
+    /// In `SchedulingMode::Recurrence`, late reviews are credited with the days past the due
+    /// date; see [`Item::grade_with_elapsed`]. Lateness is measured from the due date, so fuzzed
+    /// and load balanced due dates are respected. Early reviews are graded according to the
+    /// `EarlyReviewPolicy` of `config`.
+    pub fn review_at_with_config(
+        &self,
+        quality: u8,
+        now: D,
+        config: &Sm2Config,
+    ) -> Result<Self, Error> {
+        let quality = Quality::try_from(quality)?;
+        let elapsed_days = self
+            .last_review
+            .map(|last_review| usize::try_from(now.days_since(last_review)).unwrap_or(0));
+
+        if self.is_early(now) {
+            match config.early_review {
+                EarlyReviewPolicy::Full => {}
+                EarlyReviewPolicy::Ignore => {
+                    return Ok(Self {
+                        item: self.item.drill(quality.value())?,
+                        ..*self
+                    })
+                }
+                EarlyReviewPolicy::Damped if config.is_pass(quality) => {
+                    let interval = self.scheduled_interval().unwrap_or(0);
+                    let fraction = elapsed_days.unwrap_or(0) as f64 / interval.max(1) as f64;
+                    let item = self.item.grade_damped(quality, fraction, config);
+                    return Ok(Self::from_last_review_with_config(item, now, config));
+                }
+                EarlyReviewPolicy::Damped => {}
+            }
+        }
+
+        let days_late = usize::try_from(self.days_overdue(now)).unwrap_or(0);
+        let item = self.item.grade_late(quality, days_late, config);
+        Ok(Self::from_last_review_with_config(item, now, config))
+    }
+
+    /// Returns a new `Scheduled` after reviewing the item with the given quality on `now`,
+    /// with the new interval fuzzed using `rng` to spread out reviews. See [`fuzz_interval`].
+    /// The fuzzed interval still respects the maximum interval of `config`, and reviews ignored
+    /// by its `EarlyReviewPolicy` are not rescheduled.
+    pub fn review_at_fuzzed<R: RandomSource + ?Sized>(
+        &self,
+        quality: u8,
+        now: D,
+        config: &Sm2Config,
+        rng: &mut R,
+    ) -> Result<Self, Error> {
+        let scheduled = self.review_at_with_config(quality, now, config)?;
+        if self.ignores_review(now, config) {
+            return Ok(scheduled);
+        }
+        let interval = fuzz_interval(scheduled.scheduled_interval().unwrap_or(0), rng)
+            .min(config.max_interval.unwrap_or(usize::MAX));
+
+        Ok(Self {
+            due: Some(now.add_days(interval)),
+            ..scheduled
+        })
+    }
+
+    /// Returns a new `Scheduled` after reviewing the item with the given quality on `now`,
+    /// with the new due date moved to the least busy day within the fuzz range of the interval.
+    ///
+    /// `due_count` returns the number of reviews already due on a given day. Ties are broken in
+    /// favour of the day closest to the unfuzzed interval, then the earlier day. See [`fuzz_range`].
+    /// Reviews ignored by the `EarlyReviewPolicy` of `config` are not rescheduled.
+    pub fn review_at_balanced<F: FnMut(D) -> usize>(
+        &self,
+        quality: u8,
+        now: D,
+        config: &Sm2Config,
+        mut due_count: F,
+    ) -> Result<Self, Error> {
+        let scheduled = self.review_at_with_config(quality, now, config)?;
+        if self.ignores_review(now, config) {
+            return Ok(scheduled);
+        }
+        let target = scheduled.scheduled_interval().unwrap_or(0);
+        let max_interval = config.max_interval.unwrap_or(usize::MAX);
+        let interval = fuzz_range(target)
+            .filter(|&interval| interval <= max_interval)
+            .min_by_key(|&interval| {
+                let distance = (interval as i128 - target as i128).abs();
+                (due_count(now.add_days(interval)), distance, interval)
+            })
+            .unwrap_or(target);
+
+        Ok(Self {
+            due: Some(now.add_days(interval)),
+            ..scheduled
+        })
+    }
+
+    /// Returns a new `Scheduled` after drilling the item on the day of its review.
+    /// The review and due dates are left untouched. See [`Item::drill`].
+    pub fn drill(&self, quality: u8) -> Result<Self, Error> {
+        Ok(Self {
+            item: self.item.drill(quality)?,
+            ..*self
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_item_is_due_without_due_date() {
+        let scheduled = Scheduled::<u64>::default();
+        assert_eq!(scheduled.due_date(), None);
+        assert!(scheduled.is_due(0));
+        assert_eq!(scheduled.days_overdue(100), 0);
+    }
+
+    #[test]
+    fn review_at_stamps_review_and_due_dates() {
+        let scheduled = Scheduled::default()
+            .review_at(4, 10)
+            .unwrap()
+            .review_at(4, 11)
+            .unwrap();
+        assert_eq!(scheduled.last_review(), Some(11));
+        assert_eq!(scheduled.due_date(), Some(17));
+        assert!(!scheduled.is_due(16));
+        assert!(scheduled.is_due(17));
+        assert_eq!(scheduled.days_overdue(14), -3);
+        assert_eq!(scheduled.days_overdue(20), 3);
+    }
+
+    #[test]
+    fn review_at_with_config_uses_config_interval() {
+        let config = Sm2Config {
+            first_interval: 3,
+            ..Sm2Config::default()
+        };
+        let scheduled = Scheduled::default()
+            .review_at_with_config(4, 10, &config)
+            .unwrap();
+        assert_eq!(scheduled.due_date(), Some(13));
+    }
+
+    #[test]
+    fn late_review_is_credited_in_recurrence_mode() {
+        let config = Sm2Config {
+            mode: crate::SchedulingMode::Recurrence,
+            ..Sm2Config::default()
+        };
+        let scheduled = Scheduled::from_last_review_with_config(Item::new(3, 2.5), 0, &config);
+        assert_eq!(scheduled.due_date(), Some(15));
+
+        let on_time = scheduled.review_at_with_config(4, 15, &config).unwrap();
+        assert_eq!(on_time.due_date(), Some(15 + 38));
+        let late = scheduled.review_at_with_config(4, 60, &config).unwrap();
+        assert_eq!(late.due_date(), Some(60 + 150));
+
+        // The closed form cannot take the lateness into account.
+        let scheduled = Scheduled::from_last_review(Item::new(3, 2.5), 0);
+        let late = scheduled.review_at(4, 60).unwrap();
+        assert_eq!(late.item().last_interval(), late.item().interval());
+        assert_eq!(late.due_date(), Some(60 + 38));
+    }
+
+    #[test]
+    fn lateness_is_measured_from_fuzzed_due_date() {
+        let config = Sm2Config {
+            mode: crate::SchedulingMode::Recurrence,
+            interval_multiplier: 2.0,
+            ..Sm2Config::default()
+        };
+        let scheduled = Scheduled::from_last_review_with_config(Item::new(3, 2.5), 0, &config);
+        assert_eq!(scheduled.due_date(), Some(30));
+        let on_time = scheduled.review_at_with_config(4, 30, &config).unwrap();
+        assert_eq!(on_time.scheduled_interval(), Some(76));
+
+        let mut rng = crate::SplitMix64::new(5);
+        for _ in 0..10 {
+            let fuzzed = scheduled
+                .review_at_fuzzed(4, 30, &config, &mut rng)
+                .unwrap();
+            let due = fuzzed.due_date().unwrap();
+            let reviewed = fuzzed.review_at_with_config(4, due, &config).unwrap();
+            let expected = fuzzed
+                .item()
+                .grade_with_config(Quality::CorrectHesitant, &config);
+            assert_eq!(*reviewed.item(), expected);
+
+            let late = fuzzed.review_at_with_config(4, due + 10, &config).unwrap();
+            assert_eq!(
+                late.item().last_interval(),
+                ((38.0 + 5.0) * 2.5f64).ceil() as usize
+            );
+        }
+    }
+
+    #[test]
+    fn early_reviews_follow_policy() {
+        let scheduled = Scheduled::from_last_review(Item::new(3, 2.0), 0);
+        assert_eq!(scheduled.due_date(), Some(12));
+        assert!(!Scheduled::<u64>::default().is_early(0));
+        assert!(scheduled.is_early(3));
+        assert!(!scheduled.is_early(12));
+
+        let full = scheduled.review_at(5, 3).unwrap();
+        assert_eq!(full.item().repetitions(), 4);
+
+        let ignore = Sm2Config {
+            early_review: EarlyReviewPolicy::Ignore,
+            ..Sm2Config::default()
+        };
+        let ignored = scheduled.review_at_with_config(5, 3, &ignore).unwrap();
+        assert_eq!(ignored, scheduled);
+        let ignored = scheduled.review_at_with_config(1, 3, &ignore).unwrap();
+        assert_eq!(ignored.due_date(), Some(12));
+        assert!(ignored.item().needs_drill());
+        assert!(scheduled.ignores_review(3, &ignore));
+        assert!(!scheduled.ignores_review(12, &ignore));
+
+        let mut rng = crate::SplitMix64::new(3);
+        let fuzzed = scheduled.review_at_fuzzed(5, 3, &ignore, &mut rng).unwrap();
+        assert_eq!(fuzzed, scheduled);
+        let balanced = scheduled.review_at_balanced(5, 3, &ignore, |_| 0).unwrap();
+        assert_eq!(balanced, scheduled);
+
+        let damped = Sm2Config {
+            early_review: EarlyReviewPolicy::Damped,
+            ..Sm2Config::default()
+        };
+        let reviewed = scheduled.review_at_with_config(5, 3, &damped).unwrap();
+        assert_eq!(reviewed.item().repetitions(), 3);
+        assert!((reviewed.item().efactor() - 2.025).abs() < 1e-9);
+        assert_eq!(reviewed.last_review(), Some(3));
+        assert_eq!(reviewed.due_date(), Some(3 + 13));
+        let failed = scheduled.review_at_with_config(1, 3, &damped).unwrap();
+        assert_eq!(failed, scheduled.review_at(1, 3).unwrap());
+    }
+
+    #[test]
+    fn review_at_fuzzed_stays_in_range() {
+        let config = Sm2Config::default();
+        let scheduled = Scheduled::from_last_review(Item::new(2, 2.5), 0);
+        let mut rng = crate::SplitMix64::new(1);
+        for _ in 0..20 {
+            let fuzzed = scheduled.review_at_fuzzed(4, 6, &config, &mut rng).unwrap();
+            assert_eq!(fuzzed.item(), scheduled.review_at(4, 6).unwrap().item());
+            assert!(crate::fuzz_range(15).contains(&fuzzed.scheduled_interval().unwrap()));
+        }
+
+        let capped = Sm2Config {
+            max_interval: Some(15),
+            ..config
+        };
+        let fuzzed = scheduled.review_at_fuzzed(4, 6, &capped, &mut rng).unwrap();
+        assert!(fuzzed.scheduled_interval().unwrap() <= 15);
+    }
+
+    #[test]
+    fn review_at_balanced_picks_least_busy_day() {
+        let config = Sm2Config::default();
+        let scheduled = Scheduled::from_last_review(Item::new(2, 2.5), 0);
+        // The unfuzzed interval is 15 days, so the window is 13..=17 days after day 6.
+        let busy = |day: u64| if day == 19 { 1 } else { 5 };
+        let balanced = scheduled.review_at_balanced(4, 6, &config, busy).unwrap();
+        assert_eq!(balanced.due_date(), Some(19));
+
+        let balanced = scheduled.review_at_balanced(4, 6, &config, |_| 0).unwrap();
+        assert_eq!(balanced, scheduled.review_at(4, 6).unwrap());
+
+        let capped = Sm2Config {
+            max_interval: Some(14),
+            ..config
+        };
+        let quiet_late = |day: u64| if day >= 22 { 0 } else { 3 };
+        let balanced = scheduled
+            .review_at_balanced(4, 6, &capped, quiet_late)
+            .unwrap();
+        assert_eq!(balanced.due_date(), Some(20));
+    }
+
+    #[test]
+    fn u64_dates_saturate() {
+        assert_eq!(u64::MAX.add_days(10), u64::MAX);
+        assert_eq!(0u64.days_since(u64::MAX), -i64::MAX);
+
+        let scheduled = Scheduled::from_last_review(Item::new(2000, 2.5), 10);
+        assert_eq!(scheduled.due_date(), Some(u64::MAX));
+        assert_eq!(scheduled.days_overdue(10), -i64::MAX);
+    }
+
+    #[test]
+    fn due_date_uses_modifier_and_cap() {
+        let config = Sm2Config {
+            interval_multiplier: 0.5,
+            max_interval: Some(100),
+            ..Sm2Config::default()
+        };
+        let scheduled = Scheduled::from_last_review_with_config(Item::new(3, 2.5), 10, &config);
+        assert_eq!(scheduled.due_date(), Some(18));
+        let scheduled = Scheduled::from_last_review_with_config(Item::new(20, 2.5), 10, &config);
+        assert_eq!(scheduled.due_date(), Some(110));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_due_date() {
+        use chrono::NaiveDate;
+
+        let last_review = NaiveDate::from_ymd_opt(2021, 2, 25).unwrap();
+        let scheduled = Scheduled::from_last_review(Item::new(2, 2.5), last_review);
+        assert_eq!(scheduled.due_date(), NaiveDate::from_ymd_opt(2021, 3, 3));
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use crate::{AnkiCard, AnkiSm2, Item, Quality, Sm2Config};
+
+pub(crate) const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Return a `Duration` of the given number of days, saturating on overflow.
+pub(crate) fn days(days: u64) -> Duration {
+    Duration::from_secs(days.saturating_mul(SECONDS_PER_DAY))
+}
+
+/// Return the number of whole days in `duration`.
+pub(crate) fn whole_days(duration: Duration) -> u64 {
+    duration.as_secs() / SECONDS_PER_DAY
+}
+
+/// A spaced repetition algorithm.
+///
+/// This allows code such as simulators to be written once and run against any algorithm.
+pub trait Scheduler {
+    /// The per-item state kept by the algorithm.
+    type State;
+
+    /// Return the state of an item that has never been reviewed.
+    fn initial_state(&self) -> Self::State;
+
+    /// Returns the new state of an item after a review with the given quality, `elapsed` after
+    /// its previous review.
+    fn review(&self, state: &Self::State, quality: Quality, elapsed: Duration) -> Self::State;
+
+    /// Returns the time after the previous review at which the item is next due.
+    fn interval(&self, state: &Self::State) -> Duration;
+}
+
+/// The SM-2 algorithm, as implemented by `Item`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sm2 {
+    config: Sm2Config,
+}
+
+impl Sm2 {
+    /// Return an `Sm2` scheduler with the given parameters.
+    pub fn new(config: Sm2Config) -> Self {
+        Self { config }
+    }
+
+    /// Get the parameters of this scheduler.
+    pub fn config(&self) -> &Sm2Config {
+        &self.config
+    }
+}
+
+impl Scheduler for Sm2 {
+    type State = Item;
+
+    fn initial_state(&self) -> Item {
+        self.config.new_item()
+    }
+
+    fn review(&self, state: &Item, quality: Quality, elapsed: Duration) -> Item {
+        state.grade_with_elapsed(quality, whole_days(elapsed) as usize, &self.config)
+    }
+
+    fn interval(&self, state: &Item) -> Duration {
+        days(state.interval_with_config(&self.config) as u64)
+    }
+}
+
+impl Scheduler for AnkiSm2 {
+    type State = AnkiCard;
+
+    fn initial_state(&self) -> AnkiCard {
+        self.new_card()
+    }
+
+    fn review(&self, state: &AnkiCard, quality: Quality, elapsed: Duration) -> AnkiCard {
+        let elapsed_days = whole_days(elapsed).min(u32::MAX as u64) as u32;
+        AnkiSm2::review(self, state, quality.to_four_button(), elapsed_days)
+    }
+
+    fn interval(&self, state: &AnkiCard) -> Duration {
+        days(state.interval() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Review an item on time with each of the given qualities, returning the final interval.
+    fn simulate<S: Scheduler>(scheduler: &S, qualities: &[Quality]) -> Duration {
+        let mut state = scheduler.initial_state();
+        let mut elapsed = Duration::from_secs(0);
+        for quality in qualities {
+            state = scheduler.review(&state, *quality, elapsed);
+            elapsed = scheduler.interval(&state);
+        }
+        scheduler.interval(&state)
+    }
+
+    #[test]
+    fn sm2_matches_item() {
+        let qualities = [
+            Quality::CorrectHesitant,
+            Quality::CorrectHard,
+            Quality::Perfect,
+        ];
+        assert_eq!(simulate(&Sm2::default(), &qualities), days(15));
+    }
+
+    #[test]
+    fn sm2_credits_elapsed_time() {
+        let sm2 = Sm2::new(Sm2Config {
+            mode: crate::SchedulingMode::Recurrence,
+            ..Sm2Config::default()
+        });
+        let item = Item::new(3, 2.5);
+        let late = sm2.review(&item, Quality::CorrectHesitant, days(60));
+        assert_eq!(sm2.interval(&late), days(150));
+        let on_time = sm2.review(&item, Quality::CorrectHesitant, days(15));
+        assert_eq!(sm2.interval(&on_time), days(38));
+    }
+
+    #[test]
+    fn anki_through_trait() {
+        let qualities = [
+            Quality::CorrectHesitant,
+            Quality::CorrectHesitant,
+            Quality::Perfect,
+        ];
+        // 1 day on graduation, then 1 * 2.5 = 2 raised to 3 to beat the hard interval of 2,
+        // then 3 * 2.5 * 1.3 = 9.
+        assert_eq!(simulate(&AnkiSm2::default(), &qualities), days(9));
+    }
+
+    #[test]
+    fn day_conversions() {
+        assert_eq!(whole_days(days(3)), 3);
+        assert_eq!(days(u64::MAX), Duration::from_secs(u64::MAX));
+    }
+}
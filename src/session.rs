@@ -0,0 +1,249 @@
+use std::cmp::Ordering;
+use std::collections::{HashSet, VecDeque};
+use std::hash::Hash;
+
+use crate::{Date, Deck, Error, SplitMix64};
+
+/// The order in which due reviews are presented in a `Session`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QueueOrder {
+    /// The items that are the most days overdue come first.
+    MostOverdue,
+    /// The items are shuffled using the given seed.
+    Random(u64),
+    /// The items with the lowest E-factor, i.e. the hardest items, come first.
+    EFactorAscending,
+}
+
+/// The settings used to build the queue of a `Session`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionConfig {
+    /// The maximum number of never-reviewed items to introduce.
+    pub new_limit: usize,
+    /// The maximum number of due items to review.
+    pub review_limit: usize,
+    /// The order in which due items are reviewed.
+    pub order: QueueOrder,
+}
+
+impl Default for SessionConfig {
+    /// Return a `SessionConfig` with 20 new items, 200 reviews and the most overdue items first.
+    fn default() -> Self {
+        Self {
+            new_limit: 20,
+            review_limit: 200,
+            order: QueueOrder::MostOverdue,
+        }
+    }
+}
+
+/// A day's study queue for a `Deck`.
+///
+/// Due items are presented first, followed by new items. As SM-2 prescribes, any item answered
+/// with a quality below 4 is put back at the end of the queue and repeated until it scores at
+/// least 4. Only the first answer for each item is recorded as a review in the deck; the
+/// repeats are recorded as drills, which do not affect the E-factor.
+#[derive(Debug, Clone)]
+pub struct Session<K> {
+    queue: VecDeque<K>,
+    reviewed: HashSet<K>,
+}
+
+impl<K: Clone + Ord + Hash> Session<K> {
+    /// Build the queue for `now` from the items in `deck`.
+    pub fn new<D: Date>(deck: &Deck<K, D>, now: D, config: &SessionConfig) -> Self {
+        let mut reviews: Vec<_> = deck.due(now).collect();
+        // Sort by key first so that ties are broken the same way every time.
+        reviews.sort_by_key(|(key, _)| *key);
+        match config.order {
+            QueueOrder::MostOverdue => {
+                reviews.sort_by_key(|(_, card)| -card.scheduled().days_overdue(now));
+            }
+            QueueOrder::Random(seed) => SplitMix64::new(seed).shuffle(&mut reviews),
+            QueueOrder::EFactorAscending => reviews.sort_by(|(_, a), (_, b)| {
+                a.item()
+                    .efactor()
+                    .partial_cmp(&b.item().efactor())
+                    .unwrap_or(Ordering::Equal)
+            }),
+        }
+
+        let mut new: Vec<_> = deck.new_items(now).map(|(key, _)| key).collect();
+        new.sort();
+
+        let queue = reviews
+            .into_iter()
+            .map(|(key, _)| key)
+            .take(config.review_limit)
+            .chain(new.into_iter().take(config.new_limit))
+            .cloned()
+            .collect();
+
+        Self {
+            queue,
+            reviewed: HashSet::new(),
+        }
+    }
+
+    /// Get the key of the next item to study, or `None` if the session is finished.
+    pub fn next(&self) -> Option<&K> {
+        self.queue.front()
+    }
+
+    /// Get the number of answers still needed to finish the session, counting each queued
+    /// repeat separately.
+    pub fn remaining(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns whether every item in the session has been answered with a quality of at least 4.
+    pub fn is_finished(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Answer the next item with the given quality on `now`.
+    ///
+    /// The first answer for an item is recorded in `deck` with [`Deck::review`], and any
+    /// later answers with [`Deck::drill`]. If the item still needs drilling it is queued again,
+    /// unless it has been suspended or buried, e.g. because it became a leech.
+    /// If the item has been removed from `deck`, or suspended or buried before its first answer,
+    /// it is dropped from the session and this returns an `Err`, so that the next answer goes to
+    /// the following item.
+    pub fn answer<D: Date>(
+        &mut self,
+        deck: &mut Deck<K, D>,
+        quality: u8,
+        now: D,
+    ) -> Result<(), Error> {
+        let key = match self.queue.front() {
+            Some(key) => key.clone(),
+            None => return Ok(()),
+        };
+        let error = match deck.get(&key) {
+            None => Some(Error::ItemNotFoundError),
+            Some(card) if !self.reviewed.contains(&key) && !card.is_available(now) => {
+                Some(Error::CardUnavailableError)
+            }
+            Some(_) => None,
+        };
+        if let Some(error) = error {
+            self.queue.pop_front();
+            return Err(error);
+        }
+
+        let card = if self.reviewed.contains(&key) {
+            deck.drill(&key, quality)?
+        } else {
+            let card = deck.review(&key, quality, now)?;
+            self.reviewed.insert(key.clone());
+            card
+        };
+        let needs_drill = card.item().needs_drill() && card.is_available(now);
+
+        self.queue.pop_front();
+        if needs_drill {
+            self.queue.push_back(key);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Item, Scheduled};
+
+    fn deck() -> Deck<u32> {
+        let mut deck = Deck::new();
+        for key in 0..5 {
+            deck.insert(key, Scheduled::new(Item::default()));
+        }
+        deck.insert(10, Scheduled::from_last_review(Item::new(1, 2.5), 9));
+        deck.insert(11, Scheduled::from_last_review(Item::new(1, 1.5), 7));
+        deck.insert(12, Scheduled::from_last_review(Item::new(1, 2.0), 8));
+        deck
+    }
+
+    fn queue(session: &Session<u32>) -> Vec<u32> {
+        session.queue.iter().copied().collect()
+    }
+
+    #[test]
+    fn limits_and_order() {
+        let deck = deck();
+        let config = SessionConfig {
+            new_limit: 2,
+            review_limit: 2,
+            order: QueueOrder::MostOverdue,
+        };
+        assert_eq!(queue(&Session::new(&deck, 10, &config)), [11, 12, 0, 1]);
+
+        let config = SessionConfig {
+            new_limit: 0,
+            review_limit: 10,
+            order: QueueOrder::EFactorAscending,
+        };
+        assert_eq!(queue(&Session::new(&deck, 10, &config)), [11, 12, 10]);
+
+        let config = SessionConfig {
+            new_limit: 0,
+            review_limit: 10,
+            order: QueueOrder::Random(7),
+        };
+        let mut shuffled = queue(&Session::new(&deck, 10, &config));
+        assert_eq!(shuffled, queue(&Session::new(&deck, 10, &config)));
+        shuffled.sort();
+        assert_eq!(shuffled, [10, 11, 12]);
+    }
+
+    #[test]
+    fn items_below_four_are_repeated() {
+        let mut deck = deck();
+        let config = SessionConfig {
+            new_limit: 1,
+            review_limit: 0,
+            order: QueueOrder::MostOverdue,
+        };
+        let mut session = Session::new(&deck, 10, &config);
+        session.answer(&mut deck, 2, 10).unwrap();
+        assert_eq!(session.next(), Some(&0));
+        session.answer(&mut deck, 3, 10).unwrap();
+        assert_eq!(session.next(), Some(&0));
+        session.answer(&mut deck, 4, 10).unwrap();
+        assert!(session.is_finished());
+
+        // Only the first answer is recorded as a review.
+        let item = deck.get(&0).unwrap().item();
+        assert_eq!(item.repetitions(), 1);
+        assert_eq!(item.efactor(), Item::default().review(2).unwrap().efactor());
+        assert!(!item.needs_drill());
+    }
+
+    #[test]
+    fn removed_items_are_dropped() {
+        let mut deck = deck();
+        let config = SessionConfig {
+            new_limit: 2,
+            review_limit: 0,
+            order: QueueOrder::MostOverdue,
+        };
+        let mut session = Session::new(&deck, 10, &config);
+        deck.remove(&0);
+        assert!(session.answer(&mut deck, 4, 10).is_err());
+        assert_eq!(session.next(), Some(&1));
+        session.answer(&mut deck, 4, 10).unwrap();
+        assert!(session.is_finished());
+
+        let mut session = Session::new(&deck, 10, &config);
+        let first = *session.next().unwrap();
+        deck.suspend(&first).unwrap();
+        assert!(matches!(
+            session.answer(&mut deck, 4, 10),
+            Err(Error::CardUnavailableError)
+        ));
+        assert_ne!(session.next(), Some(&first));
+    }
+}
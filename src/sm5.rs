@@ -0,0 +1,228 @@
+use crate::{Quality, Sm2Config};
+
+/// The number of repetition rows in an `OfMatrix`. Later repetitions use the last row.
+pub const OF_MATRIX_REPETITIONS: usize = 20;
+/// The number of E-factor columns in an `OfMatrix`, covering 1.3 to 3.3 in steps of 0.1.
+pub const OF_MATRIX_EFACTORS: usize = 21;
+
+const MIN_EFACTOR: f64 = 1.3;
+
+/// A matrix of optimal factors (OF), indexed by repetition number and E-factor.
+///
+/// The optimal factor for the first repetition is the first interval in days. For later
+/// repetitions it is the factor by which the previous interval is multiplied.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OfMatrix {
+    rows: [[f64; OF_MATRIX_EFACTORS]; OF_MATRIX_REPETITIONS],
+}
+
+impl Default for OfMatrix {
+    /// Return the initial matrix, with a first interval of 4 days and later optimal factors
+    /// equal to the E-factor.
+    fn default() -> Self {
+        let mut rows = [[0.0; OF_MATRIX_EFACTORS]; OF_MATRIX_REPETITIONS];
+        for (repetition, row) in rows.iter_mut().enumerate() {
+            for (column, of) in row.iter_mut().enumerate() {
+                *of = if repetition == 0 {
+                    4.0
+                } else {
+                    Self::column_efactor(column)
+                };
+            }
+        }
+        Self { rows }
+    }
+}
+
+impl OfMatrix {
+    /// Return an `OfMatrix` from stored rows, e.g. from a previous call to [`OfMatrix::rows`].
+    pub fn from_rows(rows: [[f64; OF_MATRIX_EFACTORS]; OF_MATRIX_REPETITIONS]) -> Self {
+        Self { rows }
+    }
+
+    /// Get the rows of the matrix. Row `n` holds the optimal factors for repetition `n + 1`,
+    /// and column `m` those for an E-factor of `1.3 + 0.1 * m`.
+    pub fn rows(&self) -> &[[f64; OF_MATRIX_EFACTORS]; OF_MATRIX_REPETITIONS] {
+        &self.rows
+    }
+
+    /// Get the optimal factor for the given repetition number (from 1) and E-factor.
+    pub fn get(&self, repetition: usize, efactor: f64) -> f64 {
+        let (row, column) = Self::index(repetition, efactor);
+        self.rows[row][column]
+    }
+
+    fn get_mut(&mut self, repetition: usize, efactor: f64) -> &mut f64 {
+        let (row, column) = Self::index(repetition, efactor);
+        &mut self.rows[row][column]
+    }
+
+    fn index(repetition: usize, efactor: f64) -> (usize, usize) {
+        let row = repetition.clamp(1, OF_MATRIX_REPETITIONS) - 1;
+        // A NaN E-factor casts to column 0.
+        let column = ((efactor - MIN_EFACTOR) / 0.1)
+            .round()
+            .clamp(0.0, (OF_MATRIX_EFACTORS - 1) as f64) as usize;
+        (row, column)
+    }
+
+    fn column_efactor(column: usize) -> f64 {
+        MIN_EFACTOR + 0.1 * column as f64
+    }
+}
+
+/// The state of an item under `Sm5`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sm5Item {
+    repetitions: usize,
+    efactor: f64,
+    /// The unrounded current interval in days.
+    interval: f64,
+}
+
+impl Default for Sm5Item {
+    /// Return a default new `Sm5Item` with 0 repetitions and an E-factor of 2.5.
+    fn default() -> Self {
+        Self {
+            repetitions: 0,
+            efactor: 2.5,
+            interval: 0.0,
+        }
+    }
+}
+
+impl Sm5Item {
+    /// Return an `Sm5Item` with the given number of repetitions, E-factor and interval in days.
+    pub fn new(repetitions: usize, efactor: f64, interval: f64) -> Self {
+        Self {
+            repetitions,
+            efactor,
+            interval,
+        }
+    }
+
+    /// Get the number of repetitions of this `Sm5Item`.
+    pub fn repetitions(&self) -> usize {
+        self.repetitions
+    }
+
+    /// Get the E-factor of this `Sm5Item`.
+    pub fn efactor(&self) -> f64 {
+        self.efactor
+    }
+
+    /// Returns the current interval of the `Sm5Item` in days.
+    pub fn interval(&self) -> usize {
+        self.interval.ceil() as usize
+    }
+}
+
+/// A SuperMemo SM-5/SM-6 style scheduler.
+///
+/// Instead of multiplying intervals by the E-factor, intervals are multiplied by optimal factors
+/// from an `OfMatrix` shared by all items. Every review moves the optimal factor that produced
+/// the interval just completed towards `OF * (0.72 + q * 0.07)`, so the matrix adapts to how
+/// well the learner actually remembers. The E-factor is updated as in SM-2.
+///
+/// Because reviews update the shared matrix, `Sm5` does not implement `Scheduler`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sm5 {
+    matrix: OfMatrix,
+    /// How far each review moves an optimal factor towards its observed value, from 0 to 1.
+    learning_fraction: f64,
+}
+
+impl Default for Sm5 {
+    /// Return an `Sm5` with the initial matrix and a learning fraction of 0.5.
+    fn default() -> Self {
+        Self::new(OfMatrix::default(), 0.5)
+    }
+}
+
+impl Sm5 {
+    /// Return an `Sm5` with the given matrix and learning fraction.
+    pub fn new(matrix: OfMatrix, learning_fraction: f64) -> Self {
+        Self {
+            matrix,
+            learning_fraction,
+        }
+    }
+
+    /// Get the matrix of optimal factors, e.g. to persist or inspect it.
+    pub fn of_matrix(&self) -> &OfMatrix {
+        &self.matrix
+    }
+
+    /// Returns a new `Sm5Item` based on the given quality, updating the matrix of optimal
+    /// factors.
+    pub fn review(&mut self, item: &Sm5Item, quality: Quality) -> Sm5Item {
+        if item.repetitions > 0 {
+            let q = quality.value() as f64;
+            let of = self.matrix.get_mut(item.repetitions, item.efactor);
+            let observed = *of * (0.72 + q * 0.07);
+            *of += self.learning_fraction * (observed - *of);
+        }
+
+        let efactor = Sm2Config::default().next_efactor(item.efactor, quality);
+        let repetitions = if quality.is_pass() {
+            item.repetitions + 1
+        } else {
+            1
+        };
+        let of = self.matrix.get(repetitions, efactor);
+        let interval = if repetitions == 1 {
+            of
+        } else {
+            item.interval * of
+        };
+
+        Sm5Item {
+            repetitions,
+            efactor,
+            interval,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initial_matrix() {
+        let matrix = OfMatrix::default();
+        assert_eq!(matrix.get(1, 1.3), 4.0);
+        assert_eq!(matrix.get(1, 3.0), 4.0);
+        assert!((matrix.get(5, 2.5) - 2.5).abs() < 1e-9);
+        assert!((matrix.get(100, 10.0) - 3.3).abs() < 1e-9);
+        assert_eq!(matrix.get(0, 0.0), matrix.get(1, 1.3));
+    }
+
+    #[test]
+    fn review_updates_matrix() {
+        let mut sm5 = Sm5::default();
+        let item = sm5.review(&Sm5Item::default(), Quality::CorrectHesitant);
+        assert_eq!(item.interval(), 4);
+        assert_eq!(sm5.of_matrix(), &OfMatrix::default());
+
+        let item = sm5.review(&item, Quality::Perfect);
+        assert!((sm5.of_matrix().get(1, 2.5) - 4.14).abs() < 1e-9);
+        assert_eq!(item.repetitions(), 2);
+        assert_eq!(item.interval(), 11);
+
+        let restored = Sm5::new(OfMatrix::from_rows(*sm5.of_matrix().rows()), 0.5);
+        assert_eq!(restored, sm5);
+    }
+
+    #[test]
+    fn failed_review_restarts() {
+        let mut sm5 = Sm5::default();
+        let item = sm5.review(&Sm5Item::new(4, 2.5, 40.0), Quality::Incorrect);
+        assert_eq!(item.repetitions(), 1);
+        assert_eq!(item.interval(), 4);
+        assert!(sm5.of_matrix().get(4, 2.5) < 2.5);
+    }
+}
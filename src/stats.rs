@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+
+use crate::{Card, CardState, Date, ReviewLog, Sm2Config};
+
+/// The interval in days at which a card counts as mature rather than young.
+pub const MATURE_INTERVAL: usize = 21;
+
+/// The number of cards in each `CardState`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateCounts {
+    /// The number of cards that have never been reviewed.
+    pub new: usize,
+    /// The number of new cards that still need to be drilled.
+    pub drill: usize,
+    /// The number of cards scheduled by SM-2.
+    pub review: usize,
+    /// The number of lapsed cards that still need to be drilled.
+    pub lapse_drill: usize,
+    /// The number of suspended cards.
+    pub suspended: usize,
+    /// The number of buried cards.
+    pub buried: usize,
+}
+
+/// The share of reviews that were passed with a quality of 3 or above, over the last day, week
+/// and month. Each is `None` if there were no reviews in that period.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Retention {
+    /// The retention of reviews on the current day.
+    pub day: Option<f64>,
+    /// The retention of reviews in the last 7 days.
+    pub week: Option<f64>,
+    /// The retention of reviews in the last 30 days.
+    pub month: Option<f64>,
+}
+
+impl Retention {
+    /// Return the retention of the given reviews as of `now`.
+    fn from_logs<'a, D: Date + 'a, I: Iterator<Item = &'a ReviewLog<D>> + Clone>(
+        logs: I,
+        now: D,
+    ) -> Self {
+        let within = |days: i64| {
+            let (passed, total) = logs
+                .clone()
+                .filter(|log| (0..days).contains(&now.days_since(log.reviewed_at)))
+                .fold((0, 0), |(passed, total), log| {
+                    (passed + log.quality.is_pass() as usize, total + 1)
+                });
+            if total == 0 {
+                None
+            } else {
+                Some(passed as f64 / total as f64)
+            }
+        };
+
+        Self {
+            day: within(1),
+            week: within(7),
+            month: within(30),
+        }
+    }
+}
+
+/// Statistics about a collection of cards and their review logs.
+///
+/// Interval statistics only cover cards that have been reviewed, using the interval from
+/// `Item::interval_with_config`. Cards with an interval of at least [`MATURE_INTERVAL`] days are
+/// mature, and the rest are young.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+    /// The number of cards in each state.
+    pub state_counts: StateCounts,
+    /// The number of cards with each E-factor, keyed by the E-factor in tenths, rounded to the
+    /// nearest tenth. For example, E-factors around 2.5 are counted under 25.
+    pub efactor_histogram: BTreeMap<u32, usize>,
+    /// The number of reviewed cards with each interval in days.
+    pub interval_distribution: BTreeMap<usize, usize>,
+    /// The mean interval in days of reviewed cards, if there are any.
+    pub average_interval: Option<f64>,
+    /// The median interval in days of reviewed cards, if there are any.
+    pub median_interval: Option<f64>,
+    /// The retention of all reviews.
+    pub retention: Retention,
+    /// The retention of reviews of cards that were young at the time.
+    pub young_retention: Retention,
+    /// The retention of reviews of cards that were mature at the time.
+    pub mature_retention: Retention,
+    /// The total number of lapses of all cards.
+    pub lapses: usize,
+    /// The number of reviewed cards with an interval below [`MATURE_INTERVAL`] days.
+    pub young: usize,
+    /// The number of reviewed cards with an interval of at least [`MATURE_INTERVAL`] days.
+    pub mature: usize,
+}
+
+impl Stats {
+    /// Returns the statistics of `cards` as of `now`, using the default `Sm2Config`.
+    pub fn from_cards<'a, D, I>(cards: I, now: D) -> Self
+    where
+        D: Date + 'a,
+        I: IntoIterator<Item = &'a Card<D>>,
+    {
+        Self::from_cards_with_config(cards, now, &Sm2Config::default())
+    }
+
+    /// Returns the statistics of `cards` as of `now`, using the given `Sm2Config` to calculate
+    /// intervals.
+    pub fn from_cards_with_config<'a, D, I>(cards: I, now: D, config: &Sm2Config) -> Self
+    where
+        D: Date + 'a,
+        I: IntoIterator<Item = &'a Card<D>>,
+    {
+        let cards: Vec<_> = cards.into_iter().collect();
+        let mut stats = Self::default();
+        let mut intervals = Vec::new();
+
+        for card in &cards {
+            let counts = &mut stats.state_counts;
+            match card.state(now) {
+                CardState::New => counts.new += 1,
+                CardState::Drill { .. } => counts.drill += 1,
+                CardState::Review { .. } => counts.review += 1,
+                CardState::LapseDrill { .. } => counts.lapse_drill += 1,
+                CardState::Suspended => counts.suspended += 1,
+                CardState::Buried { .. } => counts.buried += 1,
+            }
+
+            let item = card.item();
+            let tenths = (item.efactor() * 10.0).round() as u32;
+            *stats.efactor_histogram.entry(tenths).or_insert(0) += 1;
+            stats.lapses += item.lapses();
+
+            if card.scheduled().last_review().is_some() {
+                let interval = item.interval_with_config(config);
+                *stats.interval_distribution.entry(interval).or_insert(0) += 1;
+                intervals.push(interval);
+                if interval >= MATURE_INTERVAL {
+                    stats.mature += 1;
+                } else {
+                    stats.young += 1;
+                }
+            }
+        }
+
+        if !intervals.is_empty() {
+            intervals.sort_unstable();
+            let len = intervals.len();
+            let sum: f64 = intervals.iter().map(|&interval| interval as f64).sum();
+            stats.average_interval = Some(sum / len as f64);
+            stats.median_interval = Some(if len % 2 == 0 {
+                (intervals[len / 2 - 1] as f64 + intervals[len / 2] as f64) / 2.0
+            } else {
+                intervals[len / 2] as f64
+            });
+        }
+
+        let logs = cards.iter().flat_map(|card| card.history());
+        stats.retention = Retention::from_logs(logs.clone(), now);
+        stats.young_retention = Retention::from_logs(
+            logs.clone()
+                .filter(|log| log.previous_interval < MATURE_INTERVAL),
+            now,
+        );
+        stats.mature_retention = Retention::from_logs(
+            logs.filter(|log| log.previous_interval >= MATURE_INTERVAL),
+            now,
+        );
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Item, Scheduled};
+
+    fn cards() -> Vec<Card> {
+        let mut reviewed = Card::default();
+        for (day, quality) in [(0, 4), (1, 4), (7, 2), (8, 5), (9, 3)].iter() {
+            reviewed.review_at(*quality, *day, None).unwrap();
+        }
+        let mut suspended = Card::from(Scheduled::from_last_review(Item::new(5, 2.5), 0));
+        suspended.suspend();
+
+        vec![
+            Card::default(),
+            reviewed,
+            Card::from(Scheduled::from_last_review(Item::new(4, 2.0), 0)),
+            suspended,
+        ]
+    }
+
+    #[test]
+    fn counts_and_distributions() {
+        let stats = Stats::from_cards(&cards(), 10);
+        assert_eq!(
+            stats.state_counts,
+            StateCounts {
+                new: 1,
+                drill: 0,
+                review: 2,
+                lapse_drill: 0,
+                suspended: 1,
+                buried: 0,
+            }
+        );
+        assert_eq!(
+            stats.efactor_histogram.into_iter().collect::<Vec<_>>(),
+            [(20, 1), (21, 1), (25, 2)]
+        );
+        // The reviewed card lapsed once, so it is on its third repetition: 6 * 2.14 = 13.
+        assert_eq!(
+            stats.interval_distribution.into_iter().collect::<Vec<_>>(),
+            [(13, 1), (24, 1), (94, 1)]
+        );
+        assert_eq!(stats.average_interval, Some(131.0 / 3.0));
+        assert_eq!(stats.median_interval, Some(24.0));
+        assert_eq!(stats.lapses, 1);
+        assert_eq!((stats.young, stats.mature), (1, 2));
+    }
+
+    #[test]
+    fn retention_windows() {
+        let stats = Stats::from_cards(&cards(), 10);
+        assert_eq!(stats.retention.day, None);
+        assert_eq!(stats.retention.week, Some(2.0 / 3.0));
+        assert_eq!(stats.retention.month, Some(0.8));
+        assert_eq!(stats.young_retention, stats.retention);
+        assert_eq!(stats.mature_retention, Retention::default());
+        assert_eq!(Stats::from_cards(&[] as &[Card], 10), Stats::default());
+    }
+}